@@ -1,48 +1,182 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use ab_glyph::FontVec;
 use log::error;
 
 use crate::{
-    film::LogoCache,
-    utility::font::{read_font_data, read_sub_font_data},
+    argument::ResourceArguments,
+    config::{Config, Preset},
+    film::{FontChain, LogoCache},
+    pipeline::IccProfile,
+    utility::font::{
+        read_caption_font_data, read_fallback_fonts, read_font_data, read_sub_font_data,
+        FontOptions, Typeface,
+    },
 };
 
+/// Fonts, logos & layout options to develop with.
 #[derive(Debug, Clone)]
-pub struct RustantFilmAppState {
+pub struct RustantFilmPreset {
     pub logos: Arc<LogoCache>,
-    pub font: Arc<FontVec>,
-    pub sub_font: Arc<Option<FontVec>>,
+    pub font: FontChain,
+    pub sub_font: Option<FontChain>,
+    pub caption_font: Option<FontChain>,
+    /// ICC profile of the printer, only from the config since it's a file on the server
+    pub print_profile: Option<Arc<IccProfile>>,
+    pub layout: Preset,
+}
+
+#[derive(Debug, Clone)]
+pub struct RustantFilmAppState {
+    pub default: Arc<RustantFilmPreset>,
+    pub presets: Arc<HashMap<String, RustantFilmPreset>>,
+}
+
+impl RustantFilmAppState {
+    /// Get the named preset, or the default one if no name is given.
+    pub fn preset(&self, name: Option<&str>) -> Option<&RustantFilmPreset> {
+        match name {
+            Some(n) => self.presets.get(n),
+            None => Some(&self.default),
+        }
+    }
+}
+
+/// Resources loaded so far, shared among presets using the same files.
+#[derive(Default)]
+struct Loaded {
+    logos: HashMap<String, Arc<LogoCache>>,
+    fonts: HashMap<(String, FontOptions), Arc<Typeface>>,
+    sub_fonts: HashMap<(Option<String>, FontOptions), Option<Arc<Typeface>>>,
+    caption_fonts: HashMap<(Option<String>, FontOptions), Option<Arc<Typeface>>>,
+    fallbacks: HashMap<Vec<String>, Arc<Vec<Typeface>>>,
 }
 
 pub fn build_app_state(
-    logos_dir: String,
-    font_filename: String,
-    sub_font_filename: Option<String>,
+    resources: &ResourceArguments,
+    config: &Config,
+    default_preset: Option<&str>,
 ) -> Result<RustantFilmAppState, Box<dyn std::error::Error>> {
-    // load logos from given directory
-    let mut logo_cache = LogoCache::new();
-    if let Err(e) = logo_cache.load(&logos_dir) {
-        error!("cannot read logos from file {}, cause: {}", logos_dir, e);
-        return Err(e);
+    let mut loaded = Loaded::default();
+
+    let default = load_preset(resources, config.resolve(default_preset)?, &mut loaded)?;
+    let mut presets = HashMap::new();
+    for name in config.preset.keys() {
+        let preset = load_preset(resources, config.resolve(Some(name))?, &mut loaded)?;
+        presets.insert(name.clone(), preset);
     }
 
-    let font = match read_font_data(&font_filename) {
-        Ok(f) => f,
-        Err(e) => {
-            error!(
-                "cannot load font from file: {}, cause: {}",
-                font_filename, e
-            );
-            return Err(e);
+    Ok(RustantFilmAppState {
+        default: Arc::new(default),
+        presets: Arc::new(presets),
+    })
+}
+
+/// Load the resources of the preset, flags of the server take precedence over the preset.
+fn load_preset(
+    resources: &ResourceArguments,
+    layout: Preset,
+    loaded: &mut Loaded,
+) -> Result<RustantFilmPreset, Box<dyn std::error::Error>> {
+    let mut resources = resources.clone();
+    resources.apply(&layout);
+
+    // load logos from given directory
+    let logos_dir = resources.logos().to_string();
+    let logos = match loaded.logos.get(&logos_dir) {
+        Some(l) => l.clone(),
+        None => {
+            let mut logo_cache = LogoCache::new();
+            if let Err(e) = logo_cache.load(&logos_dir) {
+                error!("cannot read logos from file {}, cause: {}", logos_dir, e);
+                return Err(e);
+            }
+            let logo_cache = Arc::new(logo_cache);
+            loaded.logos.insert(logos_dir, logo_cache.clone());
+            logo_cache
         }
     };
 
-    let sub_font = sub_font_filename.map_or(None, |sf| read_sub_font_data(&sf));
+    let font_filename = resources.font().to_string();
+    let font_key = (font_filename.clone(), resources.font_options());
+    let font = match loaded.fonts.get(&font_key) {
+        Some(f) => f.clone(),
+        None => {
+            let font = match read_font_data(&font_filename, &font_key.1) {
+                Ok(f) => Arc::new(f),
+                Err(e) => {
+                    error!(
+                        "cannot load font from file: {}, cause: {}",
+                        font_filename, e
+                    );
+                    return Err(e);
+                }
+            };
+            loaded.fonts.insert(font_key, font.clone());
+            font
+        }
+    };
 
-    Ok(RustantFilmAppState {
-        logos: Arc::new(logo_cache),
-        font: Arc::new(font),
-        sub_font: Arc::new(sub_font),
+    let sub_font = loaded
+        .sub_fonts
+        .entry((resources.sub_font.clone(), resources.sub_font_options()))
+        .or_insert_with(|| {
+            resources
+                .sub_font
+                .as_deref()
+                .and_then(|f| read_sub_font_data(f, &resources.sub_font_options()))
+                .map(Arc::new)
+        })
+        .clone();
+
+    let caption_font = loaded
+        .caption_fonts
+        .entry((resources.caption_font.clone(), resources.caption_font_options()))
+        .or_insert_with(|| {
+            resources
+                .caption_font
+                .as_deref()
+                .and_then(|f| read_caption_font_data(f, &resources.caption_font_options()))
+                .map(Arc::new)
+        })
+        .clone();
+
+    let fallbacks = match loaded.fallbacks.get(&resources.fallback_fonts) {
+        Some(f) => f.clone(),
+        None => {
+            let fallbacks = match read_fallback_fonts(&resources.fallback_fonts) {
+                Ok(f) => Arc::new(f),
+                Err(e) => {
+                    error!("{}", e);
+                    return Err(e);
+                }
+            };
+            loaded
+                .fallbacks
+                .insert(resources.fallback_fonts.clone(), fallbacks.clone());
+            fallbacks
+        }
+    };
+    let sub_font = sub_font.map(|f| FontChain::new(f, fallbacks.clone()));
+    let caption_font = caption_font.map(|f| FontChain::new(f, fallbacks.clone()));
+    let font = FontChain::new(font, fallbacks);
+
+    let print_profile = match &layout.print_profile {
+        Some(p) => match IccProfile::read(p) {
+            Ok(p) => Some(Arc::new(p)),
+            Err(e) => {
+                error!("{}", e);
+                return Err(e);
+            }
+        },
+        None => None,
+    };
+
+    Ok(RustantFilmPreset {
+        logos,
+        font,
+        sub_font,
+        caption_font,
+        print_profile,
+        layout,
     })
 }
@@ -1,72 +1,805 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display, net::IpAddr, path::PathBuf, sync::Arc};
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    entity::{position, Caption},
+    film::paint::{
+        constant::{
+            BLANK_PAINTER, DIAGONAL_PAINTER, DUEL_PAINTER, FIT_SHRINK, FIT_TRUNCATE, FIT_WRAP,
+            FORMAT_INSTAX_MINI, FORMAT_INSTAX_SQUARE, FORMAT_INSTAX_WIDE, FORMAT_POLAROID_I_TYPE,
+            ORIENTATION_HORIZONTAL, ORIENTATION_ROTATED, ORIENTATION_VERTICAL, POSITION_BOTTOM,
+            POSITION_BOTTOM_SHORT, POSITION_LEFT, POSITION_LEFT_SHORT, POSITION_MIDDLE,
+            POSITION_MIDDLE_SHORT, POSITION_RIGHT, POSITION_RIGHT_SHORT, POSITION_TOP,
+            POSITION_TOP_SHORT, PROFILE_KEEP, PROFILE_P3, PROFILE_SRGB, TRIANGLULAR_PAINTER,
+            WINDOW_CROP, WINDOW_FIT,
+        },
+        frame::Frame,
+        main_side,
+        style::LineStyle,
+        text::{Typography, TRACKING_RANGE},
+    },
+    config::Preset,
+    inspect::{FORMAT_JSON, FORMAT_TEXT},
+    pipeline::{
+        format::{DEFAULT_DPI, DPI_RANGE},
+        ColorManagement, FilmFormat, Format, IccProfile, Length, OutputProfile, Print, PrintSize,
+        Rendition,
+    },
+    utility::{
+        font::FontOptions,
+        output::{OverwritePolicy, DEFAULT_FILENAME_TEMPLATE},
+    },
+};
+
+/// Font to use if neither the flag nor the config sets one.
+pub const DEFAULT_FONT: &str = "font.ttf";
+/// Logos directory to use if neither the flag nor the config sets one.
+pub const DEFAULT_LOGOS: &str = "./logos";
+
 #[derive(Parser, Debug, Clone, Serialize, Deserialize)]
 #[command(
     name = "rustant-film",
-    version = "0.1",
+    version,
     author = "BlankZhu",
     about = "Add a instant film style layout to your EXIF photo."
 )]
 pub struct Arguments {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug, Clone, Serialize, Deserialize)]
+pub enum Command {
+    /// Develop all the photos under the input directory
+    #[command(
+        after_help = "Exit codes:\n  0  all the photos are developed or skipped\n  1  the run cannot start, e.g. the font cannot be loaded\n  2  any photo fails to develop\n  3  the report cannot be written, while no photo fails"
+    )]
+    Develop(DevelopArguments),
+    /// Run as an HTTP server
+    Serve(ServeArguments),
+    /// Print the EXIF info rustant-film extracts from photos
+    Inspect(InspectArguments),
+    /// List the available painters
+    Painters,
+    /// List the logos loaded from the logos directory
+    Logos(LogosArguments),
+    /// Watch the input directory and develop new photos as they arrive
+    Watch(WatchArguments),
+}
+
+/// Fonts & logos used to paint.
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceArguments {
     /// filename to main font to use
-    #[arg(short, long, default_value = "font.ttf", help = "filename to font to use")]
-    pub font: String,
+    #[arg(short, long, default_value = None, help = "filename to font to use, use `font.ttf` if not set by the config either")]
+    pub font: Option<String>,
+
+    /// face of the main font to use
+    #[arg(long = "font-face", default_value = None, help = "optional, face of the main font to use if it's a collection like `.ttc`, by index from 0 or by name, e.g. `1` or `Noto Sans CJK SC`")]
+    pub font_face: Option<String>,
+
+    /// variation of the main font to use
+    #[arg(long = "font-variation", default_value = None, help = "optional, variation of the main font to use if it's a variable font, a named instance like `Bold` or axis values like `wght=700,wdth=90`")]
+    pub font_variation: Option<String>,
 
     /// filename to sub font to use
     #[arg(long = "sub-font", default_value = None, help = "optional, filename to sub font to use")]
     pub sub_font: Option<String>,
 
-    /// path to directory that holds all the logos
-    #[arg(short, long, default_value = "./logos", help = "path to directory that holds all the logos")]
-    pub logos: String,
+    /// face of the sub font to use
+    #[arg(long = "sub-font-face", default_value = None, help = "optional, face of the sub font to use, like `--font-face`")]
+    pub sub_font_face: Option<String>,
 
-    /// mode of rustant-film, default is command
-    #[arg(short, long, default_value = "command", help = "working mode of rustant-film, use server mode if set as `server`")]
-    pub mode: String,
+    /// variation of the sub font to use
+    #[arg(long = "sub-font-variation", default_value = None, help = "optional, variation of the sub font to use, like `--font-variation`")]
+    pub sub_font_variation: Option<String>,
 
-    /// port for server mode
-    #[arg(long, default_value = "6400", help = "port for server mode")]
-    pub port: u32,
+    /// filename to caption font to use
+    #[arg(long = "caption-font", default_value = None, help = "optional, filename to font to write the caption in, e.g. a script or handwriting font, use the main font if not set")]
+    pub caption_font: Option<String>,
 
-    /// path to directory which stores the origin images
-    #[arg(short, long, default_value = "./input", help = "path to directory which stores the origin images")]
-    pub input: String,
+    /// face of the caption font to use
+    #[arg(long = "caption-font-face", default_value = None, help = "optional, face of the caption font to use, like `--font-face`")]
+    pub caption_font_face: Option<String>,
 
-    /// path to directory where the outcomes will be stored
-    #[arg(short, long, default_value = "./output", help = "path to directory where the outcomes will be stored")]
-    pub output: String,
+    /// variation of the caption font to use
+    #[arg(long = "caption-font-variation", default_value = None, help = "optional, variation of the caption font to use, like `--font-variation`")]
+    pub caption_font_variation: Option<String>,
+
+    /// filenames to fonts to fall back to for glyphs missing in the main & sub font
+    #[arg(long = "fallback-font", help = "optional, can be repeated, filename to font to fall back to for characters missing in the main & sub font, e.g. a CJK or symbol font, tried in the given order")]
+    pub fallback_fonts: Vec<String>,
+
+    /// path to directory that holds all the logos
+    #[arg(short, long, default_value = None, help = "path to directory that holds all the logos, use `./logos` if not set by the config either")]
+    pub logos: Option<String>,
+}
+
+/// Config file & the preset in it to use.
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct PresetArguments {
+    /// path to the config file
+    #[arg(long, default_value = None, help = "optional, path to the config file, `rustant-film.toml` in the current directory and `rustant-film/config.toml` in the user config directory are used if not set")]
+    pub config: Option<PathBuf>,
+
+    /// name of the preset in config
+    #[arg(long, default_value = None, help = "optional, name of the preset in the config to use, e.g. `insta` for `[preset.insta]`")]
+    pub preset: Option<String>,
+}
 
+/// Options of the instant-film layout.
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutArguments {
     /// painter that defines the instant-film layout
-    #[arg(short, long, default_value = None, help = "optional, painter that defines the instant-film layout, use `triangular` as default")]
+    #[arg(short, long, default_value = None, value_parser = [TRIANGLULAR_PAINTER, BLANK_PAINTER, DUEL_PAINTER, DIAGONAL_PAINTER], ignore_case = true, help = "optional, painter that defines the instant-film layout, use `triangular` as default")]
     pub painter: Option<String>,
 
     /// whether add all paddings around the image
-    #[arg(long = "pos", default_value = None, help = "optional, where to paint the description content, use [top/bottom/left/right/middle] (t/b/l/r/m for short). For some special painter this won't work, and different painter has their own implementation.")]
+    #[arg(long = "pos", default_value = None, value_parser = [POSITION_TOP, POSITION_BOTTOM, POSITION_LEFT, POSITION_RIGHT, POSITION_MIDDLE, POSITION_TOP_SHORT, POSITION_BOTTOM_SHORT, POSITION_LEFT_SHORT, POSITION_RIGHT_SHORT, POSITION_MIDDLE_SHORT], ignore_case = true, help = "optional, where to paint the description content, use [top/bottom/left/right/middle] (t/b/l/r/m for short). For some special painter this won't work, and different painter has their own implementation.")]
     pub position: Option<String>,
 
     /// whether add all paddings around the image
     #[arg(long = "pad", action = clap::ArgAction::SetTrue, help = "whether add paddings around the image")]
     pub padding: bool,
+
+    /// do not add paddings around the image even if the config says so
+    #[arg(long = "no-pad", action = clap::ArgAction::SetTrue, conflicts_with = "padding", help = "do not add paddings around the image, overriding the config")]
+    pub no_padding: bool,
+
+    /// paddings of the sides overriding the painter's
+    #[arg(long, default_value = None, help = "optional, paddings of the sides like `instax-mini,bottom=0.3short,sides=24px`; a preset of instax-mini, instax-wide, polaroid-600 or sx-70, then top, bottom, left, right, sides or all as a ratio of the long or short side (`0.05long`, `0.08short`) or pixels (`120px`), the painter's paddings are kept for the sides not set")]
+    pub frame: Option<Frame>,
+
+    /// real instant film to develop the photos as
+    #[arg(long, default_value = None, value_parser = [FORMAT_INSTAX_MINI, FORMAT_INSTAX_SQUARE, FORMAT_INSTAX_WIDE, FORMAT_POLAROID_I_TYPE], ignore_case = true, conflicts_with = "frame", help = "optional, develop the photos as a real instant film, at its physical size when printed at `--dpi`; the photo is put into the image window by `--format-fit` and the thick border goes to the side of the EXIF info, cannot be used with `--frame`")]
+    pub format: Option<String>,
+
+    /// how the photo is put into the image window of the format
+    #[arg(long = "format-fit", default_value = None, value_parser = [WINDOW_CROP, WINDOW_FIT], ignore_case = true, help = "optional, how the photo is put into the image window of `--format`, `crop` fills the window cutting around the center and `fit` keeps the whole photo with blank bars, use `crop` as default")]
+    pub format_fit: Option<String>,
+
+    /// print resolution of the format
+    #[arg(long, default_value = None, value_parser = clap::value_parser!(u32).range(*DPI_RANGE.start() as i64..=*DPI_RANGE.end() as i64), help = "optional, print resolution of `--format` & `--print-size` in dots per inch, written into JPEG & TIFF outputs, 300 as default")]
+    pub dpi: Option<u32>,
+
+    /// extra space between glyphs in 1/1000 em
+    #[arg(long, default_value = None, allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(*TRACKING_RANGE.start() as i64..=*TRACKING_RANGE.end() as i64), help = "optional, extra space between glyphs in 1/1000 em, from -500 to 1000, negative to tighten, 0 as default")]
+    pub tracking: Option<i32>,
+
+    /// apply the kerning of the fonts even if the config turns it off
+    #[arg(long, action = clap::ArgAction::SetTrue, help = "apply the kerning of the fonts, overriding the config")]
+    pub kerning: bool,
+
+    /// do not apply the kerning of the fonts
+    #[arg(long = "no-kerning", action = clap::ArgAction::SetTrue, conflicts_with = "kerning", help = "do not apply the kerning of the fonts")]
+    pub no_kerning: bool,
+
+    /// apply the ligatures of the fonts even if the config turns them off
+    #[arg(long, action = clap::ArgAction::SetTrue, help = "apply the standard ligatures of the fonts, overriding the config")]
+    pub ligatures: bool,
+
+    /// do not apply the ligatures of the fonts
+    #[arg(long = "no-ligatures", action = clap::ArgAction::SetTrue, conflicts_with = "ligatures", help = "do not apply the standard ligatures of the fonts, like `fi`")]
+    pub no_ligatures: bool,
+
+    /// orientation of the text in side borders
+    #[arg(long, default_value = None, value_parser = [ORIENTATION_HORIZONTAL, ORIENTATION_ROTATED, ORIENTATION_VERTICAL], ignore_case = true, help = "optional, orientation of the text in the side border of `duel` & `diagonal`, `rotated` turns it 90° along the long edge and `vertical` sets it as vertical CJK text, use `horizontal` as default")]
+    pub side_text: Option<String>,
+
+    /// styles of the painted lines
+    #[arg(long = "line-style", help = "optional, can be repeated, style of a painted line like `line=1,font=main,size=0.6,color=#202020,case=upper,opacity=0.8`; each key is optional, the n-th style is for the n-th line unless `line` is set, size is relative to the standard padding (0.5 as default), case is none, upper, lower or small-caps")]
+    pub line_styles: Vec<LineStyle>,
+
+    /// how lines too long for the painter are fitted in
+    #[arg(long, default_value = None, value_parser = [FIT_SHRINK, FIT_TRUNCATE, FIT_WRAP], ignore_case = true, help = "optional, how lines too long for the space of the painter are fitted in, `shrink` scales the text down, `truncate` cuts lines with an ellipsis and `wrap` breaks them between words, use `shrink` as default")]
+    pub fit: Option<String>,
 }
 
-impl Display for Arguments {
+/// Text written on the film besides the EXIF info.
+///
+/// A caption sidecar next to a photo, e.g. `photo.caption.toml`, overrides these for the photo.
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct CaptionArguments {
+    /// title of the photos
+    #[arg(long, default_value = None, help = "optional, title written on the film, e.g. `Morning Tide`, a caption sidecar `<stem>.caption.toml` next to the photo overrides it")]
+    pub title: Option<String>,
+
+    /// where the photos are taken
+    #[arg(long, default_value = None, help = "optional, location written on the film below the title, e.g. `Kamakura`")]
+    pub location: Option<String>,
+
+    /// signature below the title & location
+    #[arg(long, default_value = None, help = "optional, signature written on the film below the title & location, e.g. your name")]
+    pub signature: Option<String>,
+}
+
+/// Options to make the outputs ready for a print lab.
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct PrintArguments {
+    /// physical size of the prints
+    #[arg(long = "print-size", default_value = None, help = "optional, physical size of the prints like `4x6in` or `102x152mm`, turned to the orientation of the photo; the developed photo is fitted into it at `--dpi` with white around")]
+    pub print_size: Option<PrintSize>,
+
+    /// bleed around the prints
+    #[arg(long, default_value = None, help = "optional, bleed added around the prints like `3mm` or `0.125in`, filled by repeating the edges of the developed photo")]
+    pub bleed: Option<Length>,
+
+    /// whether draw crop marks around the prints
+    #[arg(long = "crop-marks", action = clap::ArgAction::SetTrue, help = "whether draw crop marks at the trim lines, outside the bleed")]
+    pub crop_marks: bool,
+
+    /// do not draw crop marks even if the config says so
+    #[arg(long = "no-crop-marks", action = clap::ArgAction::SetTrue, conflicts_with = "crop_marks", help = "do not draw crop marks, overriding the config")]
+    pub no_crop_marks: bool,
+
+    /// ICC profile of the printer to convert the outputs into
+    #[arg(long = "print-profile", default_value = None, help = "optional, filename of the ICC profile of the printer & paper to convert the outputs into, e.g. the one given by the lab, embedded into the outputs")]
+    pub print_profile: Option<String>,
+
+    /// ICC profile of the outputs
+    #[arg(long = "output-profile", default_value = None, value_parser = [PROFILE_SRGB, PROFILE_P3, PROFILE_KEEP], ignore_case = true, help = "optional, ICC profile of the outputs, `srgb` & `p3` convert the photo from its embedded profile and the frame from sRGB into it, `keep` keeps the profile of the photo and converts the frame into it; nothing is converted if not set")]
+    pub output_profile: Option<String>,
+}
+
+/// Options shared by `develop` & `watch`, which develop files from a directory into another.
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct BatchArguments {
+    #[command(flatten)]
+    pub presets: PresetArguments,
+
+    #[command(flatten)]
+    pub resources: ResourceArguments,
+
+    #[command(flatten)]
+    pub layout: LayoutArguments,
+
+    #[command(flatten)]
+    pub caption: CaptionArguments,
+
+    #[command(flatten)]
+    pub print: PrintArguments,
+
+    /// path to directory which stores the origin images
+    #[arg(short, long, default_value = "./input", help = "path to directory which stores the origin images")]
+    pub input: String,
+
+    /// whether walk into sub-directories of the input directory
+    #[arg(short, long, action = clap::ArgAction::SetTrue, help = "whether develop images in sub-directories of input as well, the output mirrors the input directory tree")]
+    pub recursive: bool,
+
+    /// glob patterns of files to develop
+    #[arg(long, value_delimiter = ',', help = "optional, glob patterns relative to input of files to develop, e.g. `2026/**/*.jpg`, can be repeated")]
+    pub include: Vec<String>,
+
+    /// glob patterns of files to skip
+    #[arg(long, value_delimiter = ',', help = "optional, glob patterns relative to input of files to skip, e.g. `**/raw/**`, can be repeated")]
+    pub exclude: Vec<String>,
+
+    /// extensions of files to develop
+    #[arg(long = "ext", value_delimiter = ',', help = "optional, extensions of files to develop, e.g. `jpg,jpeg,png`, all files are developed if not set")]
+    pub extensions: Vec<String>,
+
+    /// path to directory where the outcomes will be stored
+    #[arg(short, long, default_value = "./output", help = "path to directory where the outcomes will be stored")]
+    pub output: String,
+
+    /// template of output filenames
+    #[arg(long, default_value = DEFAULT_FILENAME_TEMPLATE, help = "template of output filenames, e.g. `{stem}_{painter}_{date:%Y%m%d}.{ext}`, fields: stem, ext, painter, artist, lens, maker, model, aperture, focal_length, exposure, iso, date")]
+    pub name: String,
+
+    /// renditions to encode from each painted photo
+    #[arg(long = "rendition", help = "optional, can be repeated, encode each painted photo into renditions like `size=2048,format=jpg,quality=90,suffix=_2048`; size is `full`, a long side or `<width>x<height>`, format is jpg, png, webp or tif; a single full-size JPEG is written if not set")]
+    pub renditions: Vec<Rendition>,
+
+    /// overwrite existing output files, which is the default
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["skip_existing", "suffix_on_conflict"], help = "overwrite existing output files, this is the default")]
+    pub overwrite: bool,
+
+    /// skip inputs whose output file exists
+    #[arg(long = "skip-existing", action = clap::ArgAction::SetTrue, conflicts_with = "suffix_on_conflict", help = "skip inputs whose output file already exists")]
+    pub skip_existing: bool,
+
+    /// add a numeric suffix when output file exists
+    #[arg(long = "suffix-on-conflict", action = clap::ArgAction::SetTrue, help = "append `_1`, `_2`, ... to the output filename if it already exists")]
+    pub suffix_on_conflict: bool,
+
+    /// re-develop files even if they are unchanged since the last run
+    #[arg(long, action = clap::ArgAction::SetTrue, help = "develop all files again, even if the manifest in output tells they are unchanged since the last run")]
+    pub force: bool,
+
+    /// long side to downscale photos to before painting
+    #[arg(long, default_value = None, value_parser = clap::value_parser!(u32).range(1..), help = "optional, downscale photos to this long side in pixels before painting for a fast preview, the layout stays the same relative to the photo")]
+    pub preview: Option<u32>,
+
+    /// render layout previews instead of the outputs
+    #[arg(long = "dry-run", visible_alias = "debug-layout", action = clap::ArgAction::SetTrue, help = "render low-resolution previews (or of `--preview` size) with boxes around the layout regions to `<name>.layout.jpg` instead of the outputs, the manifest is left untouched")]
+    pub dry_run: bool,
+
+    /// number of photos to develop in parallel
+    #[arg(short, long, default_value = None, value_parser = parse_jobs, help = "optional, number of photos to develop in parallel, use the number of CPUs as default")]
+    pub jobs: Option<usize>,
+
+    /// memory budget in MiB for developing photos in parallel
+    #[arg(long = "memory-limit", default_value = "2048", value_parser = clap::value_parser!(u32).range(1..), help = "memory budget in MiB, fewer photos are developed at once if they are large")]
+    pub memory_limit: u32,
+}
+
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct DevelopArguments {
+    #[command(flatten)]
+    pub batch: BatchArguments,
+
+    /// path to the report file
+    #[arg(long, default_value = None, help = "optional, write a report of every input file to this path, in CSV if it ends with `.csv`, otherwise in JSON")]
+    pub report: Option<String>,
+}
+
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct WatchArguments {
+    #[command(flatten)]
+    pub batch: BatchArguments,
+
+    /// how long a file must stay unchanged before developing it
+    #[arg(long, default_value = "1000", help = "milliseconds a new file must stay unchanged before it gets developed")]
+    pub settle: u64,
+}
+
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct ServeArguments {
+    #[command(flatten)]
+    pub presets: PresetArguments,
+
+    #[command(flatten)]
+    pub resources: ResourceArguments,
+
+    /// address to bind
+    #[arg(long, default_value = "0.0.0.0", help = "address for the server to bind, e.g. `127.0.0.1` or `::`")]
+    pub bind: IpAddr,
+
+    /// port to listen
+    #[arg(long, default_value = "6400", help = "port for the server to listen")]
+    pub port: u16,
+}
+
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct InspectArguments {
+    #[command(flatten)]
+    pub presets: PresetArguments,
+
+    /// path to directory that holds all the logos
+    #[arg(short, long, default_value = None, help = "path to directory that holds all the logos, use `./logos` if not set by the config either")]
+    pub logos: Option<String>,
+
+    /// output format
+    #[arg(long, default_value = FORMAT_TEXT, value_parser = [FORMAT_TEXT, FORMAT_JSON], help = "output format, `text` for humans or `json`")]
+    pub format: String,
+
+    /// photos to inspect
+    #[arg(required = true, help = "photos to inspect")]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct LogosArguments {
+    /// path to directory that holds all the logos
+    #[arg(short, long, default_value = "./logos", help = "path to directory that holds all the logos")]
+    pub logos: String,
+}
+
+fn parse_jobs(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err("jobs must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl ResourceArguments {
+    pub fn font(&self) -> &str {
+        self.font.as_deref().unwrap_or(DEFAULT_FONT)
+    }
+
+    pub fn logos(&self) -> &str {
+        self.logos.as_deref().unwrap_or(DEFAULT_LOGOS)
+    }
+
+    pub fn font_options(&self) -> FontOptions {
+        FontOptions {
+            face: self.font_face.clone(),
+            variation: self.font_variation.clone(),
+        }
+    }
+
+    pub fn sub_font_options(&self) -> FontOptions {
+        FontOptions {
+            face: self.sub_font_face.clone(),
+            variation: self.sub_font_variation.clone(),
+        }
+    }
+
+    pub fn caption_font_options(&self) -> FontOptions {
+        FontOptions {
+            face: self.caption_font_face.clone(),
+            variation: self.caption_font_variation.clone(),
+        }
+    }
+
+    /// Take the fonts & logos from the preset unless set by flags.
+    pub fn apply(&mut self, preset: &Preset) {
+        if self.font.is_none() {
+            self.font = preset.font.clone();
+        }
+        if self.font_face.is_none() {
+            self.font_face = preset.font_face.clone();
+        }
+        if self.font_variation.is_none() {
+            self.font_variation = preset.font_variation.clone();
+        }
+        if self.sub_font.is_none() {
+            self.sub_font = preset.sub_font.clone();
+        }
+        if self.sub_font_face.is_none() {
+            self.sub_font_face = preset.sub_font_face.clone();
+        }
+        if self.sub_font_variation.is_none() {
+            self.sub_font_variation = preset.sub_font_variation.clone();
+        }
+        if self.caption_font.is_none() {
+            self.caption_font = preset.caption_font.clone();
+        }
+        if self.caption_font_face.is_none() {
+            self.caption_font_face = preset.caption_font_face.clone();
+        }
+        if self.caption_font_variation.is_none() {
+            self.caption_font_variation = preset.caption_font_variation.clone();
+        }
+        if self.fallback_fonts.is_empty() {
+            self.fallback_fonts = preset.fallback_fonts.clone().unwrap_or_default();
+        }
+        if self.logos.is_none() {
+            self.logos = preset.logos.clone();
+        }
+    }
+}
+
+impl LayoutArguments {
+    /// Take the layout options from the preset unless set by flags.
+    pub fn apply(&mut self, preset: &Preset) {
+        if self.painter.is_none() {
+            self.painter = preset.painter.clone();
+        }
+        if self.position.is_none() {
+            self.position = preset.position.clone();
+        }
+        if !self.padding && !self.no_padding {
+            self.padding = preset.padding.unwrap_or(false);
+        }
+        // the frame & format set the same borders, one set by flags replaces both of the preset
+        if self.frame.is_none() && self.format.is_none() {
+            self.frame = preset.frame;
+            self.format = preset.format.clone();
+        }
+        if self.format_fit.is_none() {
+            self.format_fit = preset.format_fit.clone();
+        }
+        if self.dpi.is_none() {
+            self.dpi = preset.dpi;
+        }
+        if self.tracking.is_none() {
+            self.tracking = preset.tracking;
+        }
+        if !self.kerning && !self.no_kerning {
+            self.no_kerning = preset.kerning == Some(false);
+        }
+        if !self.ligatures && !self.no_ligatures {
+            self.no_ligatures = preset.ligatures == Some(false);
+        }
+        if self.side_text.is_none() {
+            self.side_text = preset.side_text.clone();
+        }
+        if self.line_styles.is_empty() {
+            self.line_styles = preset.line_styles.clone().unwrap_or_default();
+        }
+        if self.fit.is_none() {
+            self.fit = preset.fit.clone();
+        }
+    }
+
+    /// The instant film to develop as, turned so its thick border is on the side of the painter.
+    pub fn format(&self) -> Option<Format> {
+        let film = FilmFormat::by_name(self.format.as_deref()?).ok()?;
+        let side = main_side(
+            self.painter.as_deref(),
+            position::from_str(self.position.as_deref().unwrap_or("")),
+        );
+        Some(Format::new(
+            film,
+            side,
+            self.format_fit
+                .as_deref()
+                .and_then(|f| f.parse().ok())
+                .unwrap_or_default(),
+            self.dpi.unwrap_or(DEFAULT_DPI),
+        ))
+    }
+
+    /// How text is laid out by the painter.
+    pub fn typography(&self) -> Typography {
+        Typography {
+            tracking: self.tracking.unwrap_or(0),
+            kerning: !self.no_kerning,
+            ligatures: !self.no_ligatures,
+            side_text: self
+                .side_text
+                .as_deref()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            lines: self.line_styles.clone(),
+            fit: self
+                .fit
+                .as_deref()
+                .and_then(|f| f.parse().ok())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl CaptionArguments {
+    /// Take the caption from the preset unless set by flags.
+    pub fn apply(&mut self, preset: &Preset) {
+        if self.title.is_none() {
+            self.title = preset.title.clone();
+        }
+        if self.location.is_none() {
+            self.location = preset.location.clone();
+        }
+        if self.signature.is_none() {
+            self.signature = preset.signature.clone();
+        }
+    }
+
+    pub fn caption(&self) -> Caption {
+        Caption {
+            title: self.title.clone(),
+            location: self.location.clone(),
+            signature: self.signature.clone(),
+        }
+    }
+}
+
+impl PrintArguments {
+    /// Take the print options from the preset unless set by flags.
+    pub fn apply(&mut self, preset: &Preset) {
+        if self.print_size.is_none() {
+            self.print_size = preset.print_size;
+        }
+        if self.bleed.is_none() {
+            self.bleed = preset.bleed;
+        }
+        if !self.crop_marks && !self.no_crop_marks {
+            self.crop_marks = preset.crop_marks.unwrap_or(false);
+        }
+        if self.print_profile.is_none() {
+            self.print_profile = preset.print_profile.clone();
+        }
+        if self.output_profile.is_none() {
+            self.output_profile = preset.output_profile.clone();
+        }
+    }
+
+    /// The color management of the outputs, if the output profile is set.
+    pub fn color_management(&self) -> Result<Option<ColorManagement>, Box<dyn Error>> {
+        match &self.output_profile {
+            Some(p) => Ok(Some(ColorManagement::new(p.parse::<OutputProfile>()?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The print of the outputs at the DPI, if any print option or the DPI is set.
+    pub fn print(&self, dpi: Option<u32>) -> Result<Option<Print>, Box<dyn Error>> {
+        let enabled = self.print_size.is_some()
+            || self.bleed.is_some()
+            || self.crop_marks
+            || self.print_profile.is_some()
+            || dpi.is_some();
+        if !enabled {
+            return Ok(None);
+        }
+        let profile = match &self.print_profile {
+            Some(p) => Some(Arc::new(IccProfile::read(p)?)),
+            None => None,
+        };
+        Ok(Some(Print::new(
+            self.print_size,
+            dpi.unwrap_or(DEFAULT_DPI),
+            self.bleed,
+            self.crop_marks,
+            profile,
+        )))
+    }
+}
+
+impl Command {
+    /// The config & preset flags of the command, if it takes any.
+    pub fn presets(&self) -> Option<&PresetArguments> {
+        match self {
+            Command::Develop(args) => Some(&args.batch.presets),
+            Command::Watch(args) => Some(&args.batch.presets),
+            Command::Serve(args) => Some(&args.presets),
+            Command::Inspect(args) => Some(&args.presets),
+            _ => None,
+        }
+    }
+}
+
+impl InspectArguments {
+    pub fn logos(&self) -> &str {
+        self.logos.as_deref().unwrap_or(DEFAULT_LOGOS)
+    }
+
+    /// Take the logos from the preset unless set by flags.
+    pub fn apply(&mut self, preset: &Preset) {
+        if self.logos.is_none() {
+            self.logos = preset.logos.clone();
+        }
+    }
+}
+
+impl BatchArguments {
+    /// Take the options from the preset unless set by flags.
+    pub fn apply(&mut self, preset: &Preset) {
+        self.resources.apply(preset);
+        self.layout.apply(preset);
+        self.caption.apply(preset);
+        self.print.apply(preset);
+    }
+
+    pub fn overwrite_policy(&self) -> OverwritePolicy {
+        if self.skip_existing {
+            OverwritePolicy::SkipExisting
+        } else if self.suffix_on_conflict {
+            OverwritePolicy::SuffixOnConflict
+        } else {
+            OverwritePolicy::Overwrite
+        }
+    }
+}
+
+impl Display for ResourceArguments {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Font: {}, Sub-Font: {}, Logos: {}, mode: {}, port: {}, input: {}, output: {}, painter: {}, position: {}, padding: {}",
-            self.font.as_str(),
+            "Font: {} ({}), Sub-Font: {} ({}), Caption-Font: {} ({}), Fallback-Fonts: {:?}, Logos: {}",
+            self.font(),
+            self.font_options(),
             self.sub_font.as_ref().unwrap_or(&"(None)".to_string()),
-            self.logos.as_str(),
-            self.mode.as_str(),
-            self.port,
-            self.input.as_str(),
-            self.output.as_str(),
+            self.sub_font_options(),
+            self.caption_font.as_ref().unwrap_or(&"(None)".to_string()),
+            self.caption_font_options(),
+            self.fallback_fonts,
+            self.logos(),
+        )
+    }
+}
+
+impl Display for PresetArguments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "config: {}, preset: {}",
+            self.config
+                .as_ref()
+                .map_or("(None)".to_string(), |c| c.display().to_string()),
+            self.preset.as_ref().unwrap_or(&"(None)".to_string()),
+        )
+    }
+}
+
+impl Display for LayoutArguments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "painter: {}, position: {}, padding: {}, frame: {}, format: {}, format fit: {}, dpi: {}, tracking: {}, kerning: {}, ligatures: {}, side text: {}, line styles: [{}], fit: {}",
             self.painter.as_ref().unwrap_or(&"(None)".to_string()),
             self.position.as_ref().unwrap_or(&"(None)".to_string()),
             self.padding,
+            self.frame.map_or("(None)".to_string(), |f| f.to_string()),
+            self.format.as_ref().unwrap_or(&"(None)".to_string()),
+            self.format_fit.as_ref().unwrap_or(&"(None)".to_string()),
+            self.dpi.map_or("(None)".to_string(), |d| d.to_string()),
+            self.tracking.map_or("(None)".to_string(), |t| t.to_string()),
+            !self.no_kerning,
+            !self.no_ligatures,
+            self.side_text.as_ref().unwrap_or(&"(None)".to_string()),
+            self.line_styles
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+            self.fit.as_ref().unwrap_or(&"(None)".to_string()),
         )
     }
 }
+
+impl Display for PrintArguments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "print size: {}, bleed: {}, crop marks: {}, print profile: {}, output profile: {}",
+            self.print_size.map_or("(None)".to_string(), |s| s.to_string()),
+            self.bleed.map_or("(None)".to_string(), |b| b.to_string()),
+            self.crop_marks,
+            self.print_profile.as_ref().unwrap_or(&"(None)".to_string()),
+            self.output_profile.as_ref().unwrap_or(&"(None)".to_string()),
+        )
+    }
+}
+
+impl Display for BatchArguments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, {}, {}, {}, {}, input: {}, recursive: {}, include: {:?}, exclude: {:?}, extensions: {:?}, output: {}, name: {}, renditions: [{}], overwrite: {:?}, force: {}, preview: {}, dry run: {}, jobs: {}, memory limit: {}",
+            self.presets,
+            self.resources,
+            self.layout,
+            self.caption.caption(),
+            self.print,
+            self.input.as_str(),
+            self.recursive,
+            self.include,
+            self.exclude,
+            self.extensions,
+            self.output.as_str(),
+            self.name.as_str(),
+            self.renditions
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+            self.overwrite_policy(),
+            self.force,
+            self.preview.map_or("(None)".to_string(), |p| p.to_string()),
+            self.dry_run,
+            self.jobs.map_or("(None)".to_string(), |j| j.to_string()),
+            self.memory_limit,
+        )
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::Develop(args) => write!(
+                f,
+                "develop, {}, report: {}",
+                args.batch,
+                args.report.as_ref().unwrap_or(&"(None)".to_string())
+            ),
+            Command::Watch(args) => write!(f, "watch, {}, settle: {}", args.batch, args.settle),
+            Command::Serve(args) => write!(
+                f,
+                "serve, {}, {}, bind: {}, port: {}",
+                args.presets, args.resources, args.bind, args.port
+            ),
+            Command::Inspect(args) => write!(
+                f,
+                "inspect, {}, Logos: {}, format: {}, files: {:?}",
+                args.presets,
+                args.logos(),
+                args.format,
+                args.files
+            ),
+            Command::Painters => write!(f, "painters"),
+            Command::Logos(args) => write!(f, "logos, Logos: {}", args.logos),
+        }
+    }
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.command)
+    }
+}
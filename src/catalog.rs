@@ -0,0 +1,44 @@
+use log::error;
+
+use crate::{
+    argument::LogosArguments,
+    command::{EXIT_OK, EXIT_SETUP_FAILED},
+    film::{paint::PAINTERS, LogoCache},
+};
+
+/// Print all the painters with their descriptions.
+pub fn painters() -> u8 {
+    let width = PAINTERS.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, description) in PAINTERS {
+        println!("{:width$}  {}", name, description, width = width);
+    }
+    EXIT_OK
+}
+
+/// Print all the logos loaded from the logos directory with their sizes.
+///
+/// A logo is used if its name is contained by the camera maker in EXIF, case-insensitively.
+pub fn logos(args: LogosArguments) -> u8 {
+    let mut logo_cache = LogoCache::new();
+    if let Err(e) = logo_cache.load(&args.logos) {
+        error!("cannot read logos from file {}, cause: {}", args.logos, e);
+        return EXIT_SETUP_FAILED;
+    }
+
+    let logos = logo_cache.list();
+    if logos.is_empty() {
+        println!("no logo found in {}", args.logos);
+        return EXIT_OK;
+    }
+    let width = logos.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, logo) in logos {
+        println!(
+            "{:width$}  {}x{}",
+            name,
+            logo.width(),
+            logo.height(),
+            width = width
+        );
+    }
+    EXIT_OK
+}
@@ -1,236 +1,770 @@
 use std::{
-    fs::{self, File},
-    io::{BufReader, Cursor, Read},
+    fs,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use bytes::Bytes;
-use exif::Reader;
-use image::ImageEncoder;
+use image::ImageReader;
 use log::{debug, error, info, warn};
-use tokio::task;
+use tokio::{sync::Semaphore, task};
 
 use crate::{
-    argument::Arguments,
-    entity::{position, ExifInfo},
-    film::{
-        paint::{create_painter, Painter},
-        LogoCache,
+    argument::{BatchArguments, DevelopArguments},
+    entity::{position, Caption, DevelopRecord, DevelopStatus, ExifInfo},
+    film::{paint::create_painter, FontChain, FontSet, LogoCache},
+    pipeline::{
+        preview::LAYOUT_PREVIEW_LONG_SIDE, rendition, DebugLayout, Downscale, Pipeline, Rendition,
     },
     utility::{
-        decode::get_decoder,
-        font::{read_font_data, read_sub_font_data},
+        caption::read_sidecar,
+        font::{read_caption_font_data, read_fallback_fonts, read_font_data, read_sub_font_data},
+        input::{list_input_files, InputFile, InputFilter},
+        manifest::{digest, Manifest, ManifestEntry},
+        output::{append_to_stem, write_output, FilenameTemplate, OutputClaims, OverwritePolicy},
+        report::write_report,
     },
 };
 
-pub async fn run(args: Arguments) {
-    // load logos from given directory
-    let mut logo_cache = LogoCache::new();
-    if let Err(e) = logo_cache.load(&args.logos) {
-        error!("cannot read logos from file {}, cause: {}", args.logos, e);
-        return;
-    }
-    let logo_cache = Arc::new(logo_cache);
+/// Suffix appended to the file stem of layout previews, e.g. `photo.layout.jpg`.
+const LAYOUT_PREVIEW_SUFFIX: &str = ".layout";
 
-    // load the main font
-    let font = match read_font_data(&args.font) {
-        Ok(f) => f,
-        Err(e) => {
-            error!("cannot load font from file: {}, cause: {}", args.font, e);
-            return;
+/// Exit code if all the files are developed or skipped.
+pub const EXIT_OK: u8 = 0;
+/// Exit code if the run cannot even start, e.g. font or logos cannot be loaded.
+pub const EXIT_SETUP_FAILED: u8 = 1;
+/// Exit code if any of the files fails to develop.
+pub const EXIT_DEVELOP_FAILED: u8 = 2;
+/// Exit code if all the files are developed or skipped, but the report cannot be written.
+pub const EXIT_REPORT_FAILED: u8 = 3;
+
+/// Everything needed to develop files, shared among the jobs of command & watch mode.
+pub struct Darkroom {
+    pipeline: Arc<Pipeline>,
+    /// caption of all the photos, overridden by their sidecars
+    caption: Arc<Caption>,
+    output: Arc<OutputSettings>,
+    job_slots: Arc<Semaphore>,
+    memory_slots: Arc<Semaphore>,
+    jobs: usize,
+    memory_limit: u32,
+    incremental: Arc<Incremental>,
+}
+
+impl Darkroom {
+    /// Load logos & fonts, then prepare the pipeline and the output directory.
+    pub fn setup(args: &BatchArguments) -> Result<Self, String> {
+        // load logos from given directory
+        let mut logo_cache = LogoCache::new();
+        if let Err(e) = logo_cache.load(args.resources.logos()) {
+            return Err(format!(
+                "cannot read logos from file {}, cause: {}",
+                args.resources.logos(), e
+            ));
         }
-    };
-    let font = Arc::new(font);
-
-    // load the sub font
-    let sub_font = args.sub_font.map_or(None, |sf| read_sub_font_data(&sf));
-    let sub_font = Arc::new(sub_font);
-
-    // create painter
-    let painter = create_painter(
-        args.painter,
-        font,
-        sub_font,
-        logo_cache,
-        position::from_str(args.position.unwrap_or("".to_string()).as_str()),
-        args.padding,
-    );
-    let painter = Arc::new(painter);
+        let logo_cache = Arc::new(logo_cache);
 
-    // check output directory
-    let output_directory_path = Path::new(&args.output);
-    if let Err(e) = fs::create_dir_all(output_directory_path) {
-        error!("cannot create output directory, cause: {}", e);
-        return;
-    }
-    // list input files
-    let entries = match fs::read_dir(&args.input) {
-        Ok(entries) => entries,
-        Err(e) => {
-            error!("cannot list input files under {}, cause: {}", args.input, e);
-            return;
+        // load the main font
+        let font = match read_font_data(args.resources.font(), &args.resources.font_options()) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(format!(
+                    "cannot load font from file: {}, cause: {}",
+                    args.resources.font(), e
+                ))
+            }
+        };
+        let font = Arc::new(font);
+
+        // load the sub font
+        let sub_font = args
+            .resources
+            .sub_font
+            .as_deref()
+            .and_then(|f| read_sub_font_data(f, &args.resources.sub_font_options()));
+
+        // load the caption font
+        let caption_font = args
+            .resources
+            .caption_font
+            .as_deref()
+            .and_then(|f| read_caption_font_data(f, &args.resources.caption_font_options()));
+
+        // load the fallback fonts, shared by all the fonts
+        let fallbacks = match read_fallback_fonts(&args.resources.fallback_fonts) {
+            Ok(f) => Arc::new(f),
+            Err(e) => return Err(e.to_string()),
+        };
+        let sub_font = sub_font.map(|f| FontChain::new(Arc::new(f), fallbacks.clone()));
+        let caption_font = caption_font.map(|f| FontChain::new(Arc::new(f), fallbacks.clone()));
+        let font = FontChain::new(font, fallbacks);
+
+        // the format turns its thick border to the painter and overrides the frame
+        let format = args.layout.format();
+        let frame = format
+            .as_ref()
+            .map_or(args.layout.frame.unwrap_or_default(), |f| f.frame());
+
+        // create painter
+        let painter = create_painter(
+            args.layout.painter.clone(),
+            FontSet::new(font, sub_font, caption_font),
+            logo_cache,
+            position::from_str(args.layout.position.as_deref().unwrap_or("")),
+            args.layout.padding,
+            args.layout.typography(),
+            frame,
+        );
+        let mut pipeline = Pipeline::new(Arc::from(painter));
+        if let Some(format) = format {
+            pipeline = pipeline.with_hook(Arc::new(format));
         }
-    };
+        match args.print.color_management() {
+            Ok(Some(c)) => pipeline = pipeline.with_hook(Arc::new(c)),
+            Ok(None) => {}
+            Err(e) => return Err(format!("cannot prepare color management, cause: {}", e)),
+        }
+        let preview = match args.dry_run {
+            true => Some(args.preview.unwrap_or(LAYOUT_PREVIEW_LONG_SIDE)),
+            false => args.preview,
+        };
+        if let Some(long_side) = preview {
+            pipeline = pipeline.with_hook(Arc::new(Downscale::new(long_side)));
+        }
+        if args.dry_run {
+            pipeline = pipeline.with_hook(Arc::new(DebugLayout));
+        }
+        // previews are never printed, which would scale them up to the print size
+        match args.print.print(args.layout.dpi) {
+            Ok(Some(print)) if preview.is_none() => pipeline = pipeline.with_hook(Arc::new(print)),
+            Ok(_) => {}
+            Err(e) => return Err(format!("cannot prepare print, cause: {}", e)),
+        }
+        let pipeline = Arc::new(pipeline);
 
-    let mut handles = Vec::new();
-    for entry in entries {
-        let entry = match entry {
-            Ok(entry) => entry,
+        // check output directory
+        let output_directory_path = Path::new(&args.output);
+        if let Err(e) = fs::create_dir_all(output_directory_path) {
+            return Err(format!("cannot create output directory, cause: {}", e));
+        }
+
+        // setup the output filename template
+        let template = match FilenameTemplate::new(&args.name) {
+            Ok(t) => t,
+            Err(e) => {
+                return Err(format!(
+                    "invalid output filename template {}, cause: {}",
+                    args.name, e
+                ))
+            }
+        };
+        // setup the renditions, checked against the filenames they are written to
+        let renditions = match args.renditions.is_empty() {
+            true => vec![Rendition::default()],
+            false => args.renditions.clone(),
+        };
+        let output = Arc::new(OutputSettings {
+            directory: args.output.clone(),
+            template,
+            // previews are always rendered again
+            policy: match args.dry_run {
+                true => OverwritePolicy::Overwrite,
+                false => args.overwrite_policy(),
+            },
+            layout_preview: args.dry_run,
+            renditions,
+            claims: OutputClaims::default(),
+        });
+        // only the extension & suffix differ between the renditions, so any photo tells
+        let sample = InputFile {
+            path: PathBuf::from("photo.jpg"),
+            relative: PathBuf::from("photo.jpg"),
+        };
+        let filenames = output
+            .paths(&sample, pipeline.painter_name(), &ExifInfo::new_none())?
+            .iter()
+            .map(|p| p.file_name().unwrap_or_default().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        rendition::check_conflicts(&output.renditions, &filenames)?;
+
+        // load the manifest of previous runs
+        let manifest_path = Manifest::path_in(&args.output);
+        let manifest = match Manifest::load(&manifest_path) {
+            Ok(m) => m,
             Err(e) => {
-                warn!("cannot get file, cause: {}, skipping...", e);
-                continue;
+                warn!(
+                    "cannot load manifest {}, developing everything, cause: {}",
+                    manifest_path.display(),
+                    e
+                );
+                Manifest::new()
             }
         };
+        let incremental = Arc::new(Incremental {
+            manifest: Mutex::new(manifest),
+            manifest_path,
+            options_hash: options_hash(args, pipeline.painter_name()),
+            force: args.force || args.dry_run,
+            readonly: args.dry_run,
+        });
 
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
+        // setup the worker pool limits
+        let jobs = args.jobs.unwrap_or_else(default_jobs).max(1);
+        let memory_limit = args.memory_limit.max(1);
+
+        Ok(Darkroom {
+            pipeline,
+            caption: Arc::new(args.caption.caption()),
+            output,
+            job_slots: Arc::new(Semaphore::new(jobs)),
+            memory_slots: Arc::new(Semaphore::new(memory_limit as usize)),
+            jobs,
+            memory_limit,
+            incremental,
+        })
+    }
+
+    /// Save the manifest so the next run can skip what's developed in this one.
+    pub async fn save_manifest(&self) {
+        // writing the file blocks, keep it off the async workers
+        let incremental = self.incremental.clone();
+        if let Err(e) = task::spawn_blocking(move || incremental.save()).await {
+            error!("failed to join manifest save, cause: {}", e);
         }
+    }
 
-        let painter_clone = painter.clone();
-        let handle = task::spawn(develop(path, painter_clone, args.output.clone()));
-        handles.push(handle);
+    /// Claim the outputs of the files in order before developing them in parallel, so the same
+    /// file is always told apart when two are mapped to the same outputs. Only possible if the
+    /// filenames do not depend on the EXIF info, otherwise they are claimed as developed.
+    pub fn claim_outputs(&self, files: &[InputFile]) {
+        if self.output.template.uses_exif() {
+            return;
+        }
+        let exif_info = ExifInfo::new_none();
+        for file in files {
+            let claimed = self
+                .output
+                .paths(file, self.painter_name(), &exif_info)
+                .and_then(|paths| self.output.claims.claim(&file.path, paths));
+            if let Err(e) = claimed {
+                warn!("cannot claim outputs of {}, cause: {}", file.path.display(), e);
+            }
+        }
     }
 
-    for handle in handles {
-        if let Err(e) = handle.await {
-            error!("failed to join future, cause: {}", e);
-            continue;
+    pub fn painter_name(&self) -> &str {
+        self.pipeline.painter_name()
+    }
+
+    /// Wait for a free job slot & enough memory, then develop the file on the blocking pool.
+    pub async fn develop(&self, file: InputFile) -> DevelopRecord {
+        let mut record = DevelopRecord::new(
+            file.path.display().to_string(),
+            self.painter_name().to_string(),
+        );
+
+        let _job = match self.job_slots.clone().acquire_owned().await {
+            Ok(p) => p,
+            Err(e) => {
+                record.failed(format!("cannot acquire job slot, cause: {}", e));
+                return record;
+            }
+        };
+
+        // a file larger than the whole limit still gets developed, but alone
+        let cost = std::cmp::min(estimate_memory_cost(&file.path), self.memory_limit);
+        debug!("estimated memory cost for {}: {} MiB", file.path.display(), cost);
+        let _memory = match self.memory_slots.clone().acquire_many_owned(cost).await {
+            Ok(p) => p,
+            Err(e) => {
+                record.failed(format!("cannot acquire memory, cause: {}", e));
+                return record;
+            }
+        };
+
+        let input = record.input.clone();
+        let painter = record.painter.clone();
+        let pipeline = self.pipeline.clone();
+        let caption = self.caption.clone();
+        let output = self.output.clone();
+        let incremental = self.incremental.clone();
+        let handle = task::spawn_blocking(move || {
+            if let Err(e) = develop(&file, &pipeline, &caption, &output, &incremental, &mut record)
+            {
+                record.failed(e);
+            }
+            record
+        });
+        match handle.await {
+            Ok(r) => r,
+            Err(e) => {
+                let mut record = DevelopRecord::new(input, painter);
+                record.failed(format!("failed to join develop task, cause: {}", e));
+                record
+            }
         }
     }
 }
 
-async fn develop(path: PathBuf, painter: Arc<Box<dyn Painter>>, output: String) {
-    // open the input file
-    let mut file = match File::open(&path) {
-        Ok(f) => f,
+/// Hash of the options that affect the outcome, a change of them re-develops all the files.
+fn options_hash(args: &BatchArguments, painter_name: &str) -> String {
+    let file_hash = |filename: Option<&str>| {
+        filename
+            .and_then(|f| fs::read(f).ok())
+            .map(|data| digest(&data))
+            .unwrap_or_default()
+    };
+    let options = format!(
+        "version={};painter={};position={};padding={};frame={};format={};format_fit={};dpi={};typography={:?};caption={};print_size={};bleed={};crop_marks={};print_profile={};output_profile={};name={};font={};font_options={};sub_font={};sub_font_options={};caption_font={};caption_font_options={};fallback_fonts={};logos={};preview={};renditions={}",
+        env!("CARGO_PKG_VERSION"),
+        painter_name,
+        args.layout.position.as_deref().unwrap_or(""),
+        args.layout.padding,
+        args.layout.frame.unwrap_or_default(),
+        args.layout.format.as_deref().unwrap_or(""),
+        args.layout.format_fit.as_deref().unwrap_or(""),
+        args.layout.dpi.map_or(String::new(), |d| d.to_string()),
+        args.layout.typography(),
+        args.caption.caption(),
+        args.print.print_size.map_or(String::new(), |s| s.to_string()),
+        args.print.bleed.map_or(String::new(), |b| b.to_string()),
+        args.print.crop_marks,
+        file_hash(args.print.print_profile.as_deref()),
+        args.print.output_profile.as_deref().unwrap_or(""),
+        args.name,
+        file_hash(Some(args.resources.font())),
+        args.resources.font_options(),
+        file_hash(args.resources.sub_font.as_deref()),
+        args.resources.sub_font_options(),
+        file_hash(args.resources.caption_font.as_deref()),
+        args.resources.caption_font_options(),
+        args.resources
+            .fallback_fonts
+            .iter()
+            .map(|f| file_hash(Some(f)))
+            .collect::<Vec<_>>()
+            .join(","),
+        directory_hash(args.resources.logos()),
+        args.preview.map_or(String::new(), |p| p.to_string()),
+        args.renditions
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>()
+            .join(";"),
+    );
+    digest(options.as_bytes())
+}
+
+/// Hash of the files directly in the directory along with their names, like the logos.
+fn directory_hash(directory: &str) -> String {
+    let mut files = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect::<Vec<_>>(),
         Err(e) => {
-            error!("cannot open file at {}, cause: {}", path.display(), e);
-            return;
+            warn!("cannot read directory {}, cause: {}", directory, e);
+            Vec::new()
         }
     };
+    files.sort();
+    let hashes = files
+        .iter()
+        .map(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            let content = fs::read(p).map(|data| digest(&data)).unwrap_or_default();
+            format!("{}:{}", name, content)
+        })
+        .collect::<Vec<_>>();
+    digest(hashes.join(",").as_bytes())
+}
 
-    // read file into bytes data
-    let mut buffer = Vec::new();
-    if let Err(e) = file.read_to_end(&mut buffer) {
-        error!("cannot read file from {}, cause: {}", path.display(), e);
-        return;
-    }
-    let data = Bytes::from(buffer);
-    let cursor = Cursor::new(&data);
-    let mut reader = BufReader::new(cursor);
-
-    // load exif info
-    let exif = match Reader::new().read_from_container(&mut reader) {
-        Ok(exif) => exif,
+/// Build the filter that decides which input files get developed.
+pub fn build_input_filter(args: &BatchArguments) -> Result<InputFilter, String> {
+    InputFilter::new(&args.include, &args.exclude, &args.extensions)
+        .map_err(|e| format!("invalid include/exclude pattern, cause: {}", e))
+}
+
+/// Develop all the photos under the input directory, returns the exit code.
+pub async fn run(args: DevelopArguments) -> u8 {
+    let report = args.report;
+    let args = args.batch;
+    let darkroom = match Darkroom::setup(&args) {
+        Ok(d) => Arc::new(d),
         Err(e) => {
-            error!(
-                "cannot read EXIF from file {}, cause: {}",
-                path.display(),
-                e
-            );
-            return;
+            error!("{}", e);
+            return EXIT_SETUP_FAILED;
         }
     };
-    let exif_info = ExifInfo::new(&exif);
-    info!("handling exif info: {}", exif_info);
 
-    // create decoder to read image
-    let mut decoder = match get_decoder(data) {
-        Ok(d) => d,
+    // list input files
+    let filter = match build_input_filter(&args) {
+        Ok(f) => f,
         Err(e) => {
-            warn!(
-                "cannot get decoder from file {}, cause: {}",
-                path.display(),
-                e
-            );
-            return;
+            error!("{}", e);
+            return EXIT_SETUP_FAILED;
         }
     };
-
-    // get the potential ICC
-    let icc_profile = match decoder.icc_profile() {
-        Ok(p) => p,
+    let files = match list_input_files(&args.input, args.recursive, &filter, &args.output) {
+        Ok(files) => files,
         Err(e) => {
-            warn!(
-                "cannot get ICC profile from file {}, cause: {}",
-                path.display(),
-                e
-            );
-            return;
+            error!("cannot list input files under {}, cause: {}", args.input, e);
+            return EXIT_SETUP_FAILED;
         }
     };
-    if icc_profile.is_none() {
-        debug!("no embedding ICC profile in {}", path.display());
-    }
-
-    // decode the image into RgbImage
-    let (width, height) = decoder.dimensions();
-    let color_type = decoder.color_type();
-    if color_type != image::ColorType::Rgb8 {
-        warn!("cannot handle color type {:?}, skipping...", color_type);
-        return;
-    }
-    let total_bytes = decoder.total_bytes() as usize;
-    let mut buffer = vec![0u8; total_bytes];
-    if let Err(e) = decoder.read_image_boxed(&mut buffer) {
-        warn!(
-            "cannot read image from file {}, cause: {}",
-            path.display(),
-            e
-        );
-        return;
+
+    darkroom.claim_outputs(&files);
+    info!(
+        "developing {} files with {} jobs within {} MiB memory",
+        files.len(),
+        darkroom.jobs,
+        darkroom.memory_limit
+    );
+    let mut handles = Vec::new();
+    for file in files.iter() {
+        let darkroom = darkroom.clone();
+        let file = file.clone();
+        let handle = task::spawn(async move { darkroom.develop(file).await });
+        handles.push(handle);
     }
-    let mut image = match image::RgbImage::from_raw(width, height, buffer) {
-        Some(img) => img,
-        None => {
-            warn!("cannot decode image from file {}", path.display());
-            return;
+
+    // report in the same order as the inputs
+    let total = handles.len();
+    let mut records = Vec::with_capacity(total);
+    for (index, (file, handle)) in files.iter().zip(handles).enumerate() {
+        let record = match handle.await {
+            Ok(r) => r,
+            Err(e) => {
+                let mut record = DevelopRecord::new(
+                    file.path.display().to_string(),
+                    darkroom.painter_name().to_string(),
+                );
+                record.failed(format!("failed to join future, cause: {}", e));
+                record
+            }
+        };
+        let reason = record.reason.as_deref().unwrap_or("");
+        match record.status {
+            DevelopStatus::Developed => info!(
+                "[{}/{}] developed {} into {}",
+                index + 1,
+                total,
+                record.input,
+                record.outputs.join(", ")
+            ),
+            DevelopStatus::Skipped => {
+                warn!("[{}/{}] skipped {}, {}", index + 1, total, record.input, reason)
+            }
+            DevelopStatus::Failed => error!(
+                "[{}/{}] cannot develop {}, {}",
+                index + 1,
+                total,
+                record.input,
+                reason
+            ),
         }
+        records.push(record);
+    }
+
+    darkroom.save_manifest().await;
+    summarize(&records);
+    let mut code = if records.iter().any(|r| r.status == DevelopStatus::Failed) {
+        EXIT_DEVELOP_FAILED
+    } else {
+        EXIT_OK
     };
+    if let Some(report) = &report {
+        match write_report(report, &records) {
+            Ok(()) => info!("report written to {}", report),
+            Err(e) => {
+                error!("cannot write report to {}, cause: {}", report, e);
+                // a failed develop tells more than a failed report
+                if code == EXIT_OK {
+                    code = EXIT_REPORT_FAILED;
+                }
+            }
+        }
+    }
+    code
+}
 
-    // paint the image
-    if let Err(e) = painter.paint(&mut image, &exif_info) {
-        error!("cannot paint image {}, cause: {}", path.display(), e);
-        return;
+/// Log how many files are developed, skipped or failed, along with the reasons.
+fn summarize(records: &[DevelopRecord]) {
+    let count = |status: DevelopStatus| records.iter().filter(|r| r.status == status).count();
+    info!(
+        "summary: {} processed, {} developed, {} skipped, {} failed",
+        records.len(),
+        count(DevelopStatus::Developed),
+        count(DevelopStatus::Skipped),
+        count(DevelopStatus::Failed)
+    );
+    for record in records {
+        let reason = record.reason.as_deref().unwrap_or("");
+        match record.status {
+            DevelopStatus::Skipped => debug!("  skipped {}: {}", record.input, reason),
+            DevelopStatus::Failed => error!("  failed {}: {}", record.input, reason),
+            DevelopStatus::Developed => {}
+        }
     }
+}
+
+/// Number of jobs to use if not given, which is the number of CPUs available.
+fn default_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Estimate how many MiB developing the file takes, based on its pixel dimensions.
+///
+/// The decoded buffer, the padded canvas and the encoding buffer are alive at the same time,
+/// so take 3 times of the raw RGB size plus the file itself.
+fn estimate_memory_cost(path: &Path) -> u32 {
+    let file_size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let pixels = ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .ok()
+        .and_then(|r| r.into_dimensions().ok())
+        .map(|(width, height)| width as u64 * height as u64)
+        .unwrap_or(0);
+    let bytes = pixels * 3 * 3 + file_size;
+    std::cmp::max(bytes.div_ceil(1024 * 1024), 1) as u32
+}
+
+/// State for skipping files that are unchanged since the last run.
+struct Incremental {
+    manifest: Mutex<Manifest>,
+    manifest_path: PathBuf,
+    options_hash: String,
+    /// re-develop everything regardless of the manifest
+    force: bool,
+    /// leave the manifest untouched, e.g. when rendering layout previews
+    readonly: bool,
+}
 
-    // prepare to save the image
-    let stem = match path.file_stem() {
-        Some(s) => s.to_string_lossy().to_string(),
-        None => {
-            error!("cannot get stem name from {}", path.display());
+impl Incremental {
+    fn save(&self) {
+        if self.readonly {
             return;
         }
-    };
-    let output_filename = format!("{}/{}.jpg", output, stem);
-    let outupt_file = match File::create(&output_filename) {
-        Ok(f) => f,
-        Err(e) => {
+        let manifest = match self.manifest.lock() {
+            Ok(m) => m,
+            Err(e) => {
+                error!("cannot lock manifest, cause: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = manifest.save(&self.manifest_path) {
             error!(
-                "cannot create output file at {}, cause: {}",
-                output_filename, e
+                "cannot save manifest to {}, cause: {}",
+                self.manifest_path.display(),
+                e
             );
-            return;
         }
+    }
+}
+
+/// Where & how the developed photos are saved.
+struct OutputSettings {
+    directory: String,
+    template: FilenameTemplate,
+    policy: OverwritePolicy,
+    /// render low-resolution layout previews instead of the outputs
+    layout_preview: bool,
+    /// encoded from a single paint, each into its own file
+    renditions: Vec<Rendition>,
+    claims: OutputClaims,
+}
+
+impl OutputSettings {
+    /// Output paths of the renditions of the file, mirroring the input directory tree.
+    fn paths(
+        &self,
+        file: &InputFile,
+        painter: &str,
+        exif_info: &ExifInfo,
+    ) -> Result<Vec<PathBuf>, String> {
+        let stem = match file.relative.file_stem() {
+            Some(s) => s.to_string_lossy().to_string(),
+            None => return Err("cannot get stem name".to_string()),
+        };
+        let mut output_directory = PathBuf::from(&self.directory);
+        if let Some(parent) = file.relative.parent() {
+            output_directory.push(parent);
+        }
+        let paths = self
+            .renditions
+            .iter()
+            .map(|rendition| {
+                let filename =
+                    self.template
+                        .render(&stem, rendition.format.extension(), painter, exif_info);
+                let mut suffix = rendition.suffix.clone();
+                if self.layout_preview {
+                    suffix.push_str(LAYOUT_PREVIEW_SUFFIX);
+                }
+                output_directory.join(append_to_stem(&filename, &suffix))
+            })
+            .collect();
+        Ok(paths)
+    }
+}
+
+/// Develop a single file, filling the outcome into the record.
+fn develop(
+    file: &InputFile,
+    pipeline: &Pipeline,
+    caption: &Caption,
+    output: &OutputSettings,
+    incremental: &Incremental,
+    record: &mut DevelopRecord,
+) -> Result<(), String> {
+    // read file into bytes data
+    let buffer = fs::read(&file.path).map_err(|e| format!("cannot read file, cause: {}", e))?;
+
+    // the caption sidecar overrides the caption given by flags
+    let mut caption = caption.clone();
+    let sidecar = read_sidecar(&file.path).map_err(|e| e.to_string())?;
+    if let Some((sidecar_caption, _)) = &sidecar {
+        caption.merge(sidecar_caption.clone());
+    }
+
+    // skip the file if nothing changes since the last run, including its caption sidecar
+    let relative = file.relative.to_string_lossy().replace('\\', "/");
+    let input_hash = match &sidecar {
+        Some((_, content)) => format!("{}+{}", digest(&buffer), digest(content.as_bytes())),
+        None => digest(&buffer),
     };
+    if !incremental.force {
+        let manifest = incremental
+            .manifest
+            .lock()
+            .map_err(|e| format!("cannot lock manifest, cause: {}", e))?;
+        if let Some(entry) =
+            manifest.get_up_to_date(&relative, &input_hash, &incremental.options_hash)
+        {
+            record.outputs = entry.outputs.clone();
+            record.skipped("unchanged since last run".to_string());
+            return Ok(());
+        }
+    }
+
+    // load exif info first, which the output filename may depend on
+    let loaded = pipeline
+        .load(Bytes::from(buffer), caption)
+        .map_err(|e| e.to_string())?;
+    debug!(
+        "developing with exif info: {}, caption: {}",
+        loaded.exif_info, loaded.caption
+    );
+    record.exif = Some(loaded.exif_info.clone());
+
+    // prepare the output filenames of the renditions, not written by any other input
+    let output_paths = output.paths(file, pipeline.painter_name(), &loaded.exif_info)?;
+    let output_paths = output.claims.claim(&file.path, output_paths)?;
+    if output.policy == OverwritePolicy::SkipExisting && output_paths.iter().all(|p| p.exists()) {
+        record.skipped(format!("{} already exists", join_paths(&output_paths)));
+        return Ok(());
+    }
+
+    // run the rest of the develop pipeline, painting once for all the renditions
+    let decoded = pipeline.decode(loaded).map_err(|e| e.to_string())?;
+    let painted = pipeline.paint(decoded).map_err(|e| e.to_string())?;
+    let encoded = pipeline
+        .encode_renditions(painted, &output.renditions)
+        .map_err(|e| e.to_string())?;
+
+    // save the images
+    let mut written = Vec::new();
+    let mut existing = Vec::new();
+    for (output_path, encoded) in output_paths.into_iter().zip(encoded) {
+        match write_output(&output_path, &encoded.data, output.policy) {
+            Ok(Some(w)) => written.push(w.display().to_string()),
+            Ok(None) => existing.push(output_path),
+            Err(e) => {
+                return Err(format!(
+                    "cannot write output file at {}, cause: {}",
+                    output_path.display(),
+                    e
+                ))
+            }
+        }
+    }
+    if written.is_empty() {
+        record.skipped(format!("{} already exists", join_paths(&existing)));
+        return Ok(());
+    }
+    if !incremental.readonly {
+        incremental
+            .manifest
+            .lock()
+            .map_err(|e| format!("cannot lock manifest, cause: {}", e))?
+            .insert(
+                relative,
+                ManifestEntry {
+                    input_hash,
+                    options_hash: incremental.options_hash.clone(),
+                    outputs: written.clone(),
+                },
+            );
+    }
+    record.developed(written);
+    Ok(())
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::argument::{Arguments, Command};
 
-    // create jpeg encoder
-    let mut encoder = image::codecs::jpeg::JpegEncoder::new(&outupt_file);
-    if let Some(profile) = icc_profile {
-        if let Err(e) = encoder.set_icc_profile(profile) {
-            warn!("cannot set ICC profile to output file which may lead to incorrect color, cause: {}", e);
+    fn batch_arguments(flags: &[&str]) -> BatchArguments {
+        let args = ["rustant-film", "develop", "-i", "in", "-o", "out"]
+            .iter()
+            .chain(flags)
+            .copied();
+        match Arguments::parse_from(args).command {
+            Command::Develop(args) => args.batch,
+            _ => unreachable!(),
         }
     }
 
-    // encode the image into file
-    if let Err(e) = encoder.encode(
-        &image.as_raw(),
-        image.width(),
-        image.height(),
-        color_type.into(),
-    ) {
-        error!("cannot encode image to {}, cause: {}", output_filename, e);
+    #[test]
+    fn options_hash_follows_logo_files() {
+        let logos = std::env::temp_dir().join(format!("rustant-film-logos-{}", std::process::id()));
+        fs::create_dir_all(&logos).unwrap();
+        fs::write(logos.join("nikon.png"), b"first").unwrap();
+        let args = batch_arguments(&["-l", logos.to_str().unwrap()]);
+
+        let hash = options_hash(&args, "triangular");
+        assert_eq!(hash, options_hash(&args, "triangular"));
+
+        // the same directory with a replaced logo develops the photos again
+        fs::write(logos.join("nikon.png"), b"second").unwrap();
+        let replaced = options_hash(&args, "triangular");
+        fs::remove_dir_all(&logos).unwrap();
+        assert_ne!(hash, replaced);
+    }
+
+    #[test]
+    fn options_hash_follows_options() {
+        let args = batch_arguments(&[]);
+        let hash = options_hash(&args, "triangular");
+        assert_ne!(hash, options_hash(&args, "duel"));
+        assert_ne!(hash, options_hash(&batch_arguments(&["--pad"]), "triangular"));
+        assert_ne!(
+            hash,
+            options_hash(&batch_arguments(&["--name", "{stem}_{painter}.{ext}"]), "triangular")
+        );
     }
 }
@@ -0,0 +1,348 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entity::position,
+    film::paint::{
+        fit::Fit,
+        frame::Frame,
+        style::LineStyle,
+        text::{Orientation, TRACKING_RANGE},
+        PAINTERS,
+    },
+    pipeline::{format::DPI_RANGE, FilmFormat, Length, OutputProfile, PrintSize, WindowFit},
+};
+
+/// Filename of the config in the project directory, i.e. the current directory.
+pub const PROJECT_CONFIG_FILENAME: &str = "rustant-film.toml";
+/// Filename of the config in the user config directory, e.g. `~/.config/rustant-film/config.toml`.
+pub const USER_CONFIG_FILENAME: &str = "config.toml";
+const CONFIG_DIRECTORY: &str = "rustant-film";
+
+/// Fonts, logos & layout options that can be set in the config.
+///
+/// Options not set are left to the command-line flags or the built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    pub font: Option<String>,
+    pub font_face: Option<String>,
+    pub font_variation: Option<String>,
+    pub sub_font: Option<String>,
+    pub sub_font_face: Option<String>,
+    pub sub_font_variation: Option<String>,
+    pub caption_font: Option<String>,
+    pub caption_font_face: Option<String>,
+    pub caption_font_variation: Option<String>,
+    pub fallback_fonts: Option<Vec<String>>,
+    pub logos: Option<String>,
+    pub painter: Option<String>,
+    pub position: Option<String>,
+    pub padding: Option<bool>,
+    pub frame: Option<Frame>,
+    pub format: Option<String>,
+    pub format_fit: Option<String>,
+    pub dpi: Option<u32>,
+    pub tracking: Option<i32>,
+    pub kerning: Option<bool>,
+    pub ligatures: Option<bool>,
+    pub side_text: Option<String>,
+    pub line_styles: Option<Vec<LineStyle>>,
+    pub fit: Option<String>,
+    pub title: Option<String>,
+    pub location: Option<String>,
+    pub signature: Option<String>,
+    pub print_size: Option<PrintSize>,
+    pub bleed: Option<Length>,
+    pub crop_marks: Option<bool>,
+    pub print_profile: Option<String>,
+    pub output_profile: Option<String>,
+}
+
+impl Preset {
+    /// Overlay the options set in other onto self.
+    pub fn merge(&mut self, other: Preset) {
+        if other.font.is_some() {
+            self.font = other.font;
+        }
+        if other.font_face.is_some() {
+            self.font_face = other.font_face;
+        }
+        if other.font_variation.is_some() {
+            self.font_variation = other.font_variation;
+        }
+        if other.sub_font.is_some() {
+            self.sub_font = other.sub_font;
+        }
+        if other.sub_font_face.is_some() {
+            self.sub_font_face = other.sub_font_face;
+        }
+        if other.sub_font_variation.is_some() {
+            self.sub_font_variation = other.sub_font_variation;
+        }
+        if other.caption_font.is_some() {
+            self.caption_font = other.caption_font;
+        }
+        if other.caption_font_face.is_some() {
+            self.caption_font_face = other.caption_font_face;
+        }
+        if other.caption_font_variation.is_some() {
+            self.caption_font_variation = other.caption_font_variation;
+        }
+        if other.fallback_fonts.is_some() {
+            self.fallback_fonts = other.fallback_fonts;
+        }
+        if other.logos.is_some() {
+            self.logos = other.logos;
+        }
+        if other.painter.is_some() {
+            self.painter = other.painter;
+        }
+        if other.position.is_some() {
+            self.position = other.position;
+        }
+        if other.padding.is_some() {
+            self.padding = other.padding;
+        }
+        // the frame & format set the same borders, either one replaces both
+        if other.frame.is_some() || other.format.is_some() {
+            self.frame = other.frame;
+            self.format = other.format;
+        }
+        if other.format_fit.is_some() {
+            self.format_fit = other.format_fit;
+        }
+        if other.dpi.is_some() {
+            self.dpi = other.dpi;
+        }
+        if other.tracking.is_some() {
+            self.tracking = other.tracking;
+        }
+        if other.kerning.is_some() {
+            self.kerning = other.kerning;
+        }
+        if other.ligatures.is_some() {
+            self.ligatures = other.ligatures;
+        }
+        if other.side_text.is_some() {
+            self.side_text = other.side_text;
+        }
+        if other.line_styles.is_some() {
+            self.line_styles = other.line_styles;
+        }
+        if other.fit.is_some() {
+            self.fit = other.fit;
+        }
+        if other.title.is_some() {
+            self.title = other.title;
+        }
+        if other.location.is_some() {
+            self.location = other.location;
+        }
+        if other.signature.is_some() {
+            self.signature = other.signature;
+        }
+        if other.print_size.is_some() {
+            self.print_size = other.print_size;
+        }
+        if other.bleed.is_some() {
+            self.bleed = other.bleed;
+        }
+        if other.crop_marks.is_some() {
+            self.crop_marks = other.crop_marks;
+        }
+        if other.print_profile.is_some() {
+            self.print_profile = other.print_profile;
+        }
+        if other.output_profile.is_some() {
+            self.output_profile = other.output_profile;
+        }
+    }
+
+    /// Check the painter, position, format, tracking, side text, fit & output profile are valid,
+    /// since they are not checked by the flag parser.
+    fn validate(&self) -> Result<(), String> {
+        if let Some(painter) = &self.painter {
+            if !PAINTERS.iter().any(|(name, _)| name.eq_ignore_ascii_case(painter)) {
+                return Err(format!(
+                    "unknown painter `{}`, use one of [{}]",
+                    painter,
+                    PAINTERS.map(|(name, _)| name).join(", ")
+                ));
+            }
+        }
+        if let Some(p) = &self.position {
+            if position::from_str(p).is_none() {
+                return Err(format!("unknown position `{}`", p));
+            }
+        }
+        if let Some(f) = &self.format {
+            FilmFormat::by_name(f)?;
+            if self.frame.is_some() {
+                return Err("`frame` cannot be used with `format`".to_string());
+            }
+        }
+        if let Some(f) = &self.format_fit {
+            f.parse::<WindowFit>()?;
+        }
+        if let Some(d) = self.dpi {
+            if !DPI_RANGE.contains(&d) {
+                return Err(format!(
+                    "dpi must be {} to {}, got {}",
+                    DPI_RANGE.start(),
+                    DPI_RANGE.end(),
+                    d
+                ));
+            }
+        }
+        if let Some(t) = self.tracking {
+            if !TRACKING_RANGE.contains(&t) {
+                return Err(format!(
+                    "tracking must be {} to {}, got {}",
+                    TRACKING_RANGE.start(),
+                    TRACKING_RANGE.end(),
+                    t
+                ));
+            }
+        }
+        if let Some(s) = &self.side_text {
+            s.parse::<Orientation>()?;
+        }
+        if let Some(f) = &self.fit {
+            f.parse::<Fit>()?;
+        }
+        if let Some(p) = &self.output_profile {
+            p.parse::<OutputProfile>()?;
+        }
+        Ok(())
+    }
+
+    /// Resolve relative paths of fonts & logos against base.
+    fn resolve_paths(&mut self, base: &Path) {
+        let paths = self
+            .font
+            .iter_mut()
+            .chain(self.sub_font.iter_mut())
+            .chain(self.caption_font.iter_mut())
+            .chain(self.fallback_fonts.iter_mut().flatten())
+            .chain(self.logos.iter_mut())
+            .chain(self.print_profile.iter_mut());
+        for path in paths {
+            if Path::new(path).is_relative() {
+                *path = base.join(&*path).to_string_lossy().to_string();
+            }
+        }
+    }
+}
+
+/// Config file of rustant-film, like:
+///
+/// ```toml
+/// [default]
+/// font = "./resources/font/ttf/FiraCode-SemiBold.ttf"
+/// fallback_fonts = ["./resources/font/NotoSansCJKsc-Regular.otf"]
+/// logos = "./resources/logos"
+///
+/// [preset.insta]
+/// painter = "triangular"
+/// padding = true
+/// ```
+///
+/// Relative paths are resolved against the directory holding the config file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub default: Preset,
+    pub preset: BTreeMap<String, Preset>,
+}
+
+impl Config {
+    /// Load the config file at path if given, otherwise discover the config files.
+    ///
+    /// The user config is loaded first, then the project config overrides it. An empty config
+    /// is returned if none of them exists.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        if let Some(path) = path {
+            return Config::load_file(path);
+        }
+
+        let mut config = Config::default();
+        for path in discover() {
+            if path.is_file() {
+                config.merge(Config::load_file(&path)?);
+            }
+        }
+        Ok(config)
+    }
+
+    fn load_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("cannot read config {}, cause: {}", path.display(), e).into()),
+        };
+        let mut config: Config = match toml::from_str(&content) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("invalid config {}, cause: {}", path.display(), e).into()),
+        };
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        if let Err(e) = config.default.validate() {
+            return Err(format!("invalid config {}, cause: {}", path.display(), e).into());
+        }
+        config.default.resolve_paths(base);
+        for (name, preset) in config.preset.iter_mut() {
+            if let Err(e) = preset.validate() {
+                return Err(format!(
+                    "invalid preset `{}` in config {}, cause: {}",
+                    name,
+                    path.display(),
+                    e
+                )
+                .into());
+            }
+            preset.resolve_paths(base);
+        }
+        Ok(config)
+    }
+
+    fn merge(&mut self, other: Config) {
+        self.default.merge(other.default);
+        for (name, preset) in other.preset {
+            self.preset.entry(name).or_default().merge(preset);
+        }
+    }
+
+    /// Get the default options, with the named preset applied if given.
+    pub fn resolve(&self, preset: Option<&str>) -> Result<Preset, String> {
+        let mut ret = self.default.clone();
+        if let Some(name) = preset {
+            match self.preset.get(name) {
+                Some(p) => ret.merge(p.clone()),
+                None => {
+                    return Err(format!(
+                        "unknown preset `{}`, available presets: [{}]",
+                        name,
+                        self.preset.keys().cloned().collect::<Vec<_>>().join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Paths of the config files to look for, from the lowest priority to the highest.
+pub fn discover() -> Vec<PathBuf> {
+    let mut ret = Vec::new();
+    if let Some(dir) = dirs::config_dir() {
+        ret.push(dir.join(CONFIG_DIRECTORY).join(USER_CONFIG_FILENAME));
+    }
+    ret.push(PathBuf::from(PROJECT_CONFIG_FILENAME));
+    ret
+}
@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Text the user writes on the film besides the EXIF info, like on a real instant film.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Caption {
+    pub title: Option<String>,
+    pub location: Option<String>,
+    /// a handwritten-style line, e.g. the name of the photographer
+    pub signature: Option<String>,
+}
+
+impl Caption {
+    /// Overlay the texts set in other onto self.
+    pub fn merge(&mut self, other: Caption) {
+        if other.title.is_some() {
+            self.title = other.title;
+        }
+        if other.location.is_some() {
+            self.location = other.location;
+        }
+        if other.signature.is_some() {
+            self.signature = other.signature;
+        }
+    }
+
+    /// Lines of the caption in order: title, location & signature, empty if not set.
+    pub fn lines(&self) -> [&str; 3] {
+        [&self.title, &self.location, &self.signature].map(|t| t.as_deref().unwrap_or("").trim())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines().iter().all(|line| line.is_empty())
+    }
+}
+
+impl Display for Caption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "title: {}, location: {}, signature: {}",
+            self.title.as_deref().unwrap_or("(None)"),
+            self.location.as_deref().unwrap_or("(None)"),
+            self.signature.as_deref().unwrap_or("(None)"),
+        )
+    }
+}
@@ -1,8 +1,42 @@
 use serde::{Deserialize, Serialize};
 
+/// Bundle several renditions into a zip archive.
+pub const BUNDLE_ZIP: &str = "zip";
+/// Bundle several renditions into a multipart/mixed body.
+pub const BUNDLE_MULTIPART: &str = "multipart";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DevelopParams {
+    pub preset: Option<String>,
     pub painter: Option<String>,
     pub pos: Option<String>,
     pub pad: Option<bool>,
-}
\ No newline at end of file
+    /// paddings of the sides like `instax-mini,bottom=0.3short`
+    pub frame: Option<String>,
+    /// real instant film to develop the photo as, like `instax-mini`
+    pub format: Option<String>,
+    /// how the photo is put into the image window of the format, `crop` or `fit`
+    pub format_fit: Option<String>,
+    /// print resolution of the format in dots per inch
+    pub dpi: Option<u32>,
+    /// extra space between glyphs in 1/1000 em
+    pub tracking: Option<i32>,
+    pub kerning: Option<bool>,
+    pub ligatures: Option<bool>,
+    /// orientation of the text in side borders, `horizontal`, `rotated` or `vertical`
+    pub side_text: Option<String>,
+    /// how lines too long are fitted in, `shrink`, `truncate` or `wrap`
+    pub fit: Option<String>,
+    /// physical size of the print like `4x6in` or `102x152mm`
+    pub print_size: Option<String>,
+    /// bleed around the print like `3mm`
+    pub bleed: Option<String>,
+    pub crop_marks: Option<bool>,
+    /// ICC profile of the output, `srgb`, `p3` or `keep`
+    pub output_profile: Option<String>,
+    pub debug_layout: Option<bool>,
+    /// long side to downscale the photo to before painting
+    pub preview: Option<u32>,
+    /// how to bundle several renditions, `zip` or `multipart`
+    pub bundle: Option<String>,
+}
@@ -1,8 +1,9 @@
 use std::{fmt::Display, str::from_utf8};
 
 use exif::{Field, Value};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExifInfo {
     pub artist: Option<String>,
     pub lens_model: Option<String>,
@@ -114,3 +115,23 @@ fn get_field_as_utf8_string(field: &Field) -> String {
         .collect::<Vec<_>>()
         .join(", ")
 }
+
+/// EXIF info of a camera with all the fields set, for tests.
+#[cfg(test)]
+pub mod fixture {
+    use super::ExifInfo;
+
+    pub fn exif_info() -> ExifInfo {
+        ExifInfo {
+            artist: Some("J. Doe".to_string()),
+            lens_model: Some("RF24-105mm F4 L IS USM".to_string()),
+            camera_maker: Some("Canon".to_string()),
+            camera_model: Some("Canon EOS R5".to_string()),
+            aperture: Some("f/4.0".to_string()),
+            focal_length: Some("105mm".to_string()),
+            exposure_time: Some("1/250s".to_string()),
+            iso: Some("ISO100".to_string()),
+            datetime: Some("2026-10-19 10:00:00".to_string()),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::ExifInfo;
+
+/// Query params of the inspect API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectParams {
+    pub preset: Option<String>,
+}
+
+/// A tag as read from the EXIF of the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawTag {
+    /// IFD the tag comes from, e.g. `primary` or `thumbnail`
+    pub ifd: String,
+    pub tag: String,
+    pub value: String,
+}
+
+/// Text lines a painter would paint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PainterLines {
+    pub painter: String,
+    pub lines: Vec<String>,
+}
+
+/// Everything rustant-film gets from the EXIF of a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectReport {
+    pub file: String,
+    pub exif: ExifInfo,
+    pub tags: Vec<RawTag>,
+    /// logo picked by the camera maker
+    pub logo: Option<String>,
+    pub painters: Vec<PainterLines>,
+}
+
+impl Display for InspectReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.file)?;
+        writeln!(f, "  exif: {}", self.exif)?;
+        writeln!(f, "  logo: {}", self.logo.as_deref().unwrap_or("(None)"))?;
+        writeln!(f, "  tags:")?;
+        if self.tags.is_empty() {
+            writeln!(f, "    (None)")?;
+        }
+        for tag in &self.tags {
+            writeln!(f, "    [{}] {}: {}", tag.ifd, tag.tag, tag.value)?;
+        }
+        writeln!(f, "  painters:")?;
+        for painter in &self.painters {
+            writeln!(f, "    {}:", painter.painter)?;
+            if painter.lines.is_empty() {
+                writeln!(f, "      (None)")?;
+            }
+            for line in &painter.lines {
+                writeln!(f, "      {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
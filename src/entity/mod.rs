@@ -1,9 +1,15 @@
+pub mod caption;
 pub mod develop;
 pub mod info;
+pub mod inspect;
 pub mod position;
 pub mod padding;
+pub mod report;
 
+pub use caption::Caption;
 pub use develop::DevelopParams;
 pub use info::ExifInfo;
+pub use inspect::{InspectParams, InspectReport, PainterLines, RawTag};
 pub use position::Position;
-pub use padding::Padding;
\ No newline at end of file
+pub use padding::Padding;
+pub use report::{DevelopRecord, DevelopStatus};
\ No newline at end of file
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use super::ExifInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DevelopStatus {
+    Developed,
+    Skipped,
+    Failed,
+}
+
+/// What happened to a single input file in a batch run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevelopRecord {
+    pub input: String,
+    /// files written, one for each rendition
+    pub outputs: Vec<String>,
+    pub painter: String,
+    pub status: DevelopStatus,
+    pub exif: Option<ExifInfo>,
+    /// why the file is skipped or failed
+    pub reason: Option<String>,
+}
+
+impl DevelopRecord {
+    pub fn new(input: String, painter: String) -> Self {
+        DevelopRecord {
+            input,
+            outputs: Vec::new(),
+            painter,
+            status: DevelopStatus::Failed,
+            exif: None,
+            reason: None,
+        }
+    }
+
+    pub fn developed(&mut self, outputs: Vec<String>) {
+        self.status = DevelopStatus::Developed;
+        self.outputs = outputs;
+        self.reason = None;
+    }
+
+    pub fn skipped(&mut self, reason: String) {
+        self.status = DevelopStatus::Skipped;
+        self.reason = Some(reason);
+    }
+
+    pub fn failed(&mut self, reason: String) {
+        self.status = DevelopStatus::Failed;
+        self.reason = Some(reason);
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use ab_glyph::{Font, FontVec};
+
+use crate::utility::font::Typeface;
+
+/// A font with the fonts to fall back to, for the glyphs it does not have.
+///
+/// Fallbacks are tried in order, the first one that has the glyph renders it. Chars no font
+/// has are rendered by the main font, usually as tofu.
+#[derive(Debug, Clone)]
+pub struct FontChain {
+    font: Arc<Typeface>,
+    fallbacks: Arc<Vec<Typeface>>,
+}
+
+impl FontChain {
+    pub fn new(font: Arc<Typeface>, fallbacks: Arc<Vec<Typeface>>) -> Self {
+        FontChain { font, fallbacks }
+    }
+
+    /// The main font, which sets the line metrics.
+    pub fn primary(&self) -> &FontVec {
+        &self.font.font
+    }
+
+    /// The face of the main font, which renders the chars no font has.
+    pub fn primary_face(&self) -> &Typeface {
+        &self.font
+    }
+
+    /// The first face in the chain that has a glyph for c.
+    pub fn face_for(&self, c: char) -> Option<&Typeface> {
+        std::iter::once(self.font.as_ref())
+            .chain(self.fallbacks.iter())
+            .find(|face| face.font.glyph_id(c).0 != 0)
+    }
+
+    /// Chars in text no font of the chain can render, without duplicates.
+    pub fn missing_chars(&self, text: &str) -> Vec<char> {
+        let mut ret: Vec<char> = Vec::new();
+        for c in text.chars() {
+            if c.is_control() || ret.contains(&c) {
+                continue;
+            }
+            if self.face_for(c).is_none() {
+                ret.push(c);
+            }
+        }
+        ret
+    }
+}
+
+/// Fonts a painter paints lines in.
+#[derive(Debug, Clone)]
+pub struct FontSet {
+    pub main: FontChain,
+    /// font of the lines not emphasized, the main font is used if not given
+    pub sub: Option<FontChain>,
+    /// font of the caption, like a handwriting font, the main font is used if not given
+    pub caption: Option<FontChain>,
+}
+
+impl FontSet {
+    pub fn new(main: FontChain, sub: Option<FontChain>, caption: Option<FontChain>) -> Self {
+        FontSet { main, sub, caption }
+    }
+}
+
+/// Fonts shipped for tests under `resources/test/font`.
+#[cfg(test)]
+pub mod fixture {
+    use std::sync::Arc;
+
+    use crate::utility::font::{read_font_data, FontOptions, Typeface};
+
+    use super::{FontChain, FontSet};
+
+    /// Latin, Arabic & Hebrew.
+    pub const SANS: &str = "DejaVuSans.ttf";
+    /// Latin only.
+    pub const SANS_LIGHT: &str = "DejaVuSans-ExtraLight.ttf";
+
+    pub fn typeface(name: &str) -> Typeface {
+        let path = format!("{}/resources/test/font/{}", env!("CARGO_MANIFEST_DIR"), name);
+        read_font_data(&path, &FontOptions::default()).unwrap()
+    }
+
+    /// The font falling back to the fallbacks in order.
+    pub fn chain(name: &str, fallbacks: &[&str]) -> FontChain {
+        let fallbacks = fallbacks.iter().map(|f| typeface(f)).collect();
+        FontChain::new(Arc::new(typeface(name)), Arc::new(fallbacks))
+    }
+
+    /// All the lines in the sans font.
+    pub fn font_set() -> FontSet {
+        FontSet::new(chain(SANS, &[]), None, None)
+    }
+}
@@ -8,6 +8,12 @@ pub struct LogoCache {
     cache: collections::HashMap<String, RgbImage>,
 }
 
+impl Default for LogoCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LogoCache {
     pub fn new() -> Self {
         LogoCache { cache: collections::HashMap::new() }
@@ -30,11 +36,57 @@ impl LogoCache {
     }
 
     pub fn get(&self, logo_name: &str) -> Option<&RgbImage> {
-        for key in self.cache.keys() {
-            if logo_name.to_ascii_lowercase().contains(key) {
-                return self.cache.get(key);
-            }
-        }
-        None
+        self.resolve(logo_name).and_then(|key| self.cache.get(key))
+    }
+
+    /// Name of the logo that `get` picks for the logo name.
+    pub fn resolve(&self, logo_name: &str) -> Option<&str> {
+        let logo_name = logo_name.to_ascii_lowercase();
+        self.cache
+            .keys()
+            .find(|key| logo_name.contains(key.as_str()))
+            .map(|key| key.as_str())
     }
-}
\ No newline at end of file
+
+    /// All the logos sorted by name.
+    pub fn list(&self) -> Vec<(&str, &RgbImage)> {
+        let mut logos = self
+            .cache
+            .iter()
+            .map(|(name, logo)| (name.as_str(), logo))
+            .collect::<Vec<_>>();
+        logos.sort_by(|a, b| a.0.cmp(b.0));
+        logos
+    }
+}
+/// Logos for tests.
+#[cfg(test)]
+pub mod fixture {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use image::{Rgb, RgbImage};
+
+    use super::LogoCache;
+
+    /// A cache holding a logo many times wider than high for the maker of the EXIF fixture.
+    pub fn wide_logo_cache() -> Arc<LogoCache> {
+        // tests run in parallel, each gets its own directory
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let directory = std::env::temp_dir().join(format!(
+            "rustant-film-wide-logo-{}-{}",
+            std::process::id(),
+            CALLS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        RgbImage::from_pixel(400, 20, Rgb([200, 0, 0]))
+            .save(directory.join("canon.png"))
+            .unwrap();
+        let mut cache = LogoCache::new();
+        cache.load(directory.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        Arc::new(cache)
+    }
+}
@@ -1,4 +1,6 @@
+pub mod font;
 pub mod paint;
 pub mod logo;
 
+pub use font::{FontChain, FontSet};
 pub use logo::LogoCache;
\ No newline at end of file
@@ -1,34 +1,65 @@
+use ab_glyph::{Font, ScaleFont};
+use image::GenericImage;
 use log::debug;
 
 use crate::{
-    entity::{ExifInfo, Padding},
-    film::paint::{
-        add_padding,
-        constant::{GOLDEN_RATIO, WHITE},
+    entity::{Caption, ExifInfo, Padding, Position},
+    film::{
+        paint::{
+            add_padding,
+            caption::caption_lines,
+            constant::{BLANK_PAINTER, WHITE},
+            create_lines_canvas,
+            fit::fit_lines,
+            frame::{standard_padding, Frame},
+            layout::{Layout, RegionKind},
+            text::Typography,
+        },
+        FontSet,
     },
 };
 
 use super::Painter;
 
 pub struct BlankPainter {
+    fonts: FontSet,
     pad_around: bool,
+    typography: Typography,
+    frame: Frame,
 }
 
 impl BlankPainter {
-    pub fn new(pad_around: bool) -> Self {
-        BlankPainter { pad_around }
+    pub fn new(fonts: FontSet, pad_around: bool, typography: Typography, frame: Frame) -> Self {
+        BlankPainter {
+            fonts,
+            pad_around,
+            typography,
+            frame,
+        }
     }
 
-    pub fn new_normal() -> Self {
-        BlankPainter { pad_around: true }
+    pub fn new_normal(fonts: FontSet, typography: Typography) -> Self {
+        BlankPainter {
+            fonts,
+            pad_around: true,
+            typography,
+            frame: Frame::default(),
+        }
     }
 }
 
 impl Painter for BlankPainter {
-    fn paint(
+    fn name(&self) -> &str {
+        BLANK_PAINTER
+    }
+
+    fn paint_layout(
         &self,
         image: &mut image::RgbImage,
         _exif_info: &ExifInfo,
+        caption: &Caption,
+        scale: f32,
+        layout: &mut Layout,
     ) -> Result<(), Box<dyn std::error::Error>> {
         // get concrete values
         let (ori_width, ori_height) = image.dimensions();
@@ -36,21 +67,48 @@ impl Painter for BlankPainter {
         debug!("origin image width: {}, height: {}", ori_width, ori_height);
 
         // setup padding size
-        let padding_ratio_0 = 1.0 / GOLDEN_RATIO / 16.0;
-        let standard_padding = (long_side as f32 * padding_ratio_0) as u32; // twice of the scaled font height
+        let standard_padding = standard_padding(long_side); // twice of the scaled font height
         let main_padding = standard_padding * 3; // 3 times for main
         let mut trivial_padding: u32 = 0;
         if self.pad_around {
             trivial_padding = standard_padding; // 1 times for other
         }
-        let padding = Padding::new(
+        let mut padding = Padding::new(
             trivial_padding,
             main_padding,
             trivial_padding,
             trivial_padding,
         );
+        self.frame.apply(&mut padding, ori_width, ori_height, scale);
 
         add_padding(image, &padding, &WHITE)?;
+        layout.add_padding(ori_width, ori_height, &padding);
+
+        // write the caption in the middle of the bottom padding, like on a real instant film
+        let lines = caption_lines(caption, &self.fonts, &self.typography);
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let line_height = |scale| self.fonts.main.primary().as_scaled(scale).height() as u32;
+        let lines = fit_lines(
+            &lines,
+            standard_padding,
+            Some(image.width().saturating_sub(standard_padding * 2)),
+            Some(padding.bottom.saturating_sub(standard_padding)),
+            &self.typography,
+            |_, scale| line_height(scale),
+        );
+        let canvas = create_lines_canvas(
+            &lines,
+            Position::MIDDLE,
+            |line| line_height(line.scale),
+            WHITE,
+        );
+        let x = image.width().saturating_sub(canvas.width()) / 2;
+        let y = ori_height + padding.top + padding.bottom.saturating_sub(canvas.height()) / 2;
+        debug!("paint caption at x: {}, y: {}", x, y);
+        image.copy_from(&canvas, x, y)?;
+        layout.add(RegionKind::Caption, x, y, canvas.width(), canvas.height());
         Ok(())
     }
 }
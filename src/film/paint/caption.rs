@@ -0,0 +1,42 @@
+use crate::{
+    entity::Caption,
+    film::{
+        paint::{constant::GRAY, fit::StyledLine, style::TextStyle, text::Typography},
+        FontSet,
+    },
+};
+
+/// Index of the title in the lines of a painter, the caption comes after the 4 lines of EXIF info.
+pub const CAPTION_FIRST_LINE: usize = 4;
+
+/// Lines of the caption in their styles, without the empty ones.
+///
+/// All of them are in the caption font, the title & signature in black and the location in gray,
+/// and they are styled by the user as the lines after the painter's own.
+pub fn caption_lines<'a>(
+    caption: &'a Caption,
+    fonts: &'a FontSet,
+    typography: &Typography,
+) -> Vec<StyledLine<'a>> {
+    caption
+        .lines()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(index, text)| {
+            let default = match index {
+                1 => TextStyle {
+                    color: GRAY,
+                    ..TextStyle::caption()
+                },
+                _ => TextStyle::caption(),
+            };
+            let style = typography.line_style(CAPTION_FIRST_LINE + index, default);
+            StyledLine {
+                text,
+                font: style.font(fonts),
+                style,
+            }
+        })
+        .collect()
+}
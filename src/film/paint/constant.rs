@@ -1,5 +1,5 @@
 // math constants
-pub const GOLDEN_RATIO: f32 = 1.61803398874989484820;
+pub const GOLDEN_RATIO: f32 = 1.618_034;
 
 // color constants
 pub const BLACK: image::Rgb<u8> = image::Rgb::<u8>([0, 0, 0]);
@@ -18,6 +18,37 @@ pub const POSITION_BOTTOM_SHORT: &str = "b";
 pub const POSITION_LEFT_SHORT: &str = "l";
 pub const POSITION_RIGHT_SHORT: &str = "r";
 
+// orientation constants of side text
+pub const ORIENTATION_HORIZONTAL: &str = "horizontal";
+pub const ORIENTATION_ROTATED: &str = "rotated";
+pub const ORIENTATION_VERTICAL: &str = "vertical";
+
+// fit constants of text too long
+pub const FIT_SHRINK: &str = "shrink";
+pub const FIT_TRUNCATE: &str = "truncate";
+pub const FIT_WRAP: &str = "wrap";
+
+// frame preset constants of real instant films
+pub const FRAME_INSTAX_MINI: &str = "instax-mini";
+pub const FRAME_INSTAX_WIDE: &str = "instax-wide";
+pub const FRAME_POLAROID_600: &str = "polaroid-600";
+pub const FRAME_SX_70: &str = "sx-70";
+
+// film format constants of real instant films
+pub const FORMAT_INSTAX_MINI: &str = "instax-mini";
+pub const FORMAT_INSTAX_SQUARE: &str = "instax-square";
+pub const FORMAT_INSTAX_WIDE: &str = "instax-wide";
+pub const FORMAT_POLAROID_I_TYPE: &str = "polaroid-i-type";
+
+// fit constants of the photo into the image window of a film format
+pub const WINDOW_CROP: &str = "crop";
+pub const WINDOW_FIT: &str = "fit";
+
+// output ICC profile constants
+pub const PROFILE_SRGB: &str = "srgb";
+pub const PROFILE_P3: &str = "p3";
+pub const PROFILE_KEEP: &str = "keep";
+
 // should remove the following constants
 pub const TRIANGLULAR_PAINTER: &str = "triangular";
 pub const BLANK_PAINTER: &str = "blank";
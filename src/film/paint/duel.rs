@@ -1,67 +1,86 @@
 use std::sync::Arc;
 
-use ab_glyph::{FontVec, PxScale};
 use image::{
-    imageops::{resize, FilterType},
+    imageops::{resize, rotate270, rotate90, FilterType},
     GenericImage, Rgb, RgbImage,
 };
 use log::{debug, trace};
 
 use crate::{
-    entity::{ExifInfo, Padding, Position},
+    entity::{Caption, ExifInfo, Padding, Position},
     film::{
         paint::{
-            add_padding, add_text,
-            constant::{BLACK, GOLDEN_RATIO, GRAY, WHITE},
-            create_canvas, get_text_scaled_length,
+            add_padding,
+            caption::caption_lines,
+            constant::{DIAGONAL_PAINTER, DUEL_PAINTER, WHITE},
+            create_canvas, fit_logo,
+            layout::{Layout, RegionKind},
+            fit::{fit_columns, fit_lines, FittedLine, StyledLine},
+            frame::{standard_padding, Frame},
+            style::TextStyle,
+            text::{Orientation, TextLayout, Typography, VerticalLayout},
         },
-        LogoCache,
+        FontSet, LogoCache,
     },
 };
 
 use super::Painter;
 
+/// Lines to paint in the side border.
+struct SideText<'a> {
+    /// lines of EXIF info, without the empty ones
+    lines: Vec<StyledLine<'a>>,
+    /// number of lines of EXIF info including the empty ones, which keep their space if horizontal
+    slots: usize,
+    caption: Vec<StyledLine<'a>>,
+}
+
 /// actually, a flow (L-R) painter
 pub struct DuelPainter {
     cache: Arc<LogoCache>,
-    font: Arc<FontVec>,
-    sub_font: Arc<Option<FontVec>>,
+    fonts: FontSet,
     main_position: Position,
     diagonal: bool,
     pad_around: bool,
+    typography: Typography,
+    frame: Frame,
 }
 
 impl DuelPainter {
     pub fn new(
         cache: Arc<LogoCache>,
-        font: Arc<FontVec>,
-        sub_font: Arc<Option<FontVec>>,
+        fonts: FontSet,
         main_position: Position,
         diagonal: bool,
         pad_around: bool,
+        typography: Typography,
+        frame: Frame,
     ) -> Self {
         DuelPainter {
             cache,
-            font,
-            sub_font,
+            fonts,
             main_position,
             diagonal,
             pad_around,
+            typography,
+            frame,
         }
     }
 
-    pub fn new_normal(cache: Arc<LogoCache>, font: Arc<FontVec>) -> Self {
+    pub fn new_normal(cache: Arc<LogoCache>, fonts: FontSet, typography: Typography) -> Self {
         DuelPainter {
             cache,
-            font,
-            sub_font: None.into(),
+            fonts: FontSet { sub: None, ..fonts },
             main_position: Position::RIGHT,
             diagonal: false,
             pad_around: false,
+            typography,
+            frame: Frame::default(),
         }
     }
 
-    pub fn get_lines(&self, exif_info: &ExifInfo) -> Vec<String> {
+    /// Text lines to paint, empty lines are skipped when painting.
+    pub fn get_lines(exif_info: &ExifInfo) -> Vec<String> {
         let camera_model = exif_info
             .camera_model
             .as_deref()
@@ -127,46 +146,187 @@ impl DuelPainter {
         vec![camera_model, lens_model, detail, copyright]
     }
 
+    /// Logo & lines of the side border, with the caption after the lines, the lines are fitted
+    /// into the max width if it's set by a fixed frame.
     pub fn create_main_content_canvas(
         &self,
         exif_info: &ExifInfo,
-        font: &FontVec,
-        sub_font: &Option<FontVec>,
-        base_scale: &PxScale,
+        caption: &Caption,
+        standard_padding: u32,
+        max_width: Option<u32>,
+        max_height: u32,
         background: Rgb<u8>,
-    ) -> Result<RgbImage, Box<dyn std::error::Error>> {
-        // get the lines
-        let lines = self.get_lines(exif_info);
+    ) -> Result<(RgbImage, Layout), Box<dyn std::error::Error>> {
+        let base_scale = TextStyle::emphasized().scale(standard_padding);
 
-        // calculate the min width & height for the lines
-        let lines_height = (lines.len() + 1) as u32 * base_scale.y as u32; // +1 for a extra line space to tell logo & lines apart
-        let lines_width = lines
+        // get the lines to paint, the first one emphasized
+        let all_lines = Self::get_lines(exif_info);
+        let lines: Vec<StyledLine> = all_lines
             .iter()
-            .map(|line| get_text_scaled_length(line, font, base_scale))
-            .max()
-            .unwrap_or(0);
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .enumerate()
+            .map(|(index, (slot, line))| {
+                let default = match index {
+                    0 => TextStyle::emphasized(),
+                    _ => TextStyle::secondary(),
+                };
+                let style = self.typography.line_style(slot, default);
+                StyledLine {
+                    text: line,
+                    font: style.font(&self.fonts),
+                    style,
+                }
+            })
+            .collect();
+        let text = SideText {
+            lines,
+            slots: all_lines.len(),
+            caption: caption_lines(caption, &self.fonts, &self.typography),
+        };
 
         // get the logo
         let logo_name = exif_info.camera_maker.as_deref().unwrap_or("");
-        let logo = self.cache.get(logo_name);
-
-        let mut logo_height: u32 = 0;
-        let mut logo_width: u32 = 0;
-        if logo.is_some() {
-            let logo = logo.unwrap();
+        let logo = self.cache.get(logo_name).map(|logo| {
             let (logo_ori_width, logo_ori_height) = logo.dimensions();
             let mut logo_new_height = base_scale.y * 1.5;
             if (logo_ori_width as f32 / logo_ori_height as f32) <= 1.5 {
                 logo_new_height = base_scale.y * 3.0;
             }
             let logo_new_width = logo_new_height * logo_ori_width as f32 / logo_ori_height as f32;
-            logo_height = logo_new_height as u32;
-            logo_width = logo_new_width as u32;
+            resize(
+                logo,
+                logo_new_width as u32,
+                logo_new_height as u32,
+                FilterType::Lanczos3,
+            )
+        });
+        // a fixed side keeps its width, the logo is shrunk into it along the text
+        let logo = logo.and_then(|logo| match self.typography.side_text {
+            Orientation::Rotated => fit_logo(logo, None, max_width),
+            _ => fit_logo(logo, max_width, None),
+        });
+
+        match self.typography.side_text {
+            Orientation::Horizontal => self.create_horizontal_canvas(
+                &text,
+                logo,
+                standard_padding,
+                max_width,
+                max_height,
+                background,
+            ),
+            Orientation::Rotated => {
+                // lay the block out as a strip, then turn it so its top faces the outer edge
+                let (canvas, mut layout) = self.create_strip_canvas(
+                    &text,
+                    logo,
+                    standard_padding,
+                    max_height,
+                    max_width,
+                    background,
+                )?;
+                let canvas = match self.main_position {
+                    Position::LEFT => {
+                        layout.rotate270(canvas.width(), canvas.height());
+                        rotate270(&canvas)
+                    }
+                    _ => {
+                        layout.rotate90(canvas.width(), canvas.height());
+                        rotate90(&canvas)
+                    }
+                };
+                Ok((canvas, layout))
+            }
+            Orientation::Vertical => self.create_vertical_canvas(
+                &text,
+                logo,
+                standard_padding,
+                max_width,
+                max_height,
+                background,
+            ),
         }
+    }
+
+    /// Logo on top, lines centered below it & the caption below them, within the max size.
+    fn create_horizontal_canvas(
+        &self,
+        text: &SideText,
+        logo: Option<RgbImage>,
+        standard_padding: u32,
+        max_width: Option<u32>,
+        max_height: u32,
+        background: Rgb<u8>,
+    ) -> Result<(RgbImage, Layout), Box<dyn std::error::Error>> {
+        let mut layout = Layout::new();
+        let base_scale = TextStyle::emphasized().scale(standard_padding);
+
+        // empty lines keep their space, +1 for a extra line space to tell logo & lines apart
+        let empty_lines = text.slots - text.lines.len();
+        let spaces = empty_lines + 1 + usize::from(!text.caption.is_empty());
+
+        // the logo & spaces shrink along with the lines if all of them are too tall together
+        let full_height = logo.as_ref().map_or(0, |l| l.height())
+            + spaces as u32 * base_scale.y as u32
+            + text
+                .lines
+                .iter()
+                .chain(text.caption.iter())
+                .map(|line| line.style.scale(standard_padding).y as u32)
+                .sum::<u32>();
+        let mut space = base_scale.y as u32;
+        let mut logo = logo;
+        if full_height > max_height {
+            let factor = max_height as f32 / full_height as f32;
+            debug!("shrink logo & spaces by {} to fit height {}", factor, max_height);
+            space = (space as f32 * factor) as u32;
+            logo = logo.map(|l| {
+                let width = ((l.width() as f32 * factor) as u32).max(1);
+                let height = ((l.height() as f32 * factor) as u32).max(1);
+                resize(&l, width, height, FilterType::Lanczos3)
+            });
+        }
+        let (logo_width, logo_height) = logo.as_ref().map_or((0, 0), |l| l.dimensions());
+        let spaces_height = (empty_lines + 1) as u32 * space;
+        let max_lines_height = max_height.saturating_sub(logo_height + spaces_height);
+        let lines = fit_lines(
+            &text.lines,
+            standard_padding,
+            max_width,
+            Some(max_lines_height),
+            &self.typography,
+            |_, scale| scale.y as u32,
+        );
+        let lines_height = lines.iter().map(|line| line.scale.y as u32).sum::<u32>();
+
+        // the caption takes the height left, a line space apart from the lines
+        let caption_gap = match text.caption.is_empty() {
+            true => 0,
+            false => space,
+        };
+        let caption = fit_lines(
+            &text.caption,
+            standard_padding,
+            max_width,
+            Some(max_lines_height.saturating_sub(lines_height + caption_gap)),
+            &self.typography,
+            |_, scale| scale.y as u32,
+        );
+        let caption_height = caption.iter().map(|line| line.scale.y as u32).sum::<u32>();
+
+        // calculate the min width & height for the lines
+        let lines_height = lines_height + spaces_height;
+        let lines_width = lines
+            .iter()
+            .chain(caption.iter())
+            .map(|line| line.layout.width())
+            .max()
+            .unwrap_or(0);
 
         // create a canvas that holds the lines & logo
         let canvas_width = std::cmp::max(lines_width, logo_width);
-        let canvas_height = lines_height + logo_height;
+        let canvas_height = lines_height + logo_height + caption_gap + caption_height;
         debug!(
             "content canvas width: {}, height: {}",
             canvas_width, canvas_height
@@ -175,109 +335,359 @@ impl DuelPainter {
 
         // print logo
         let mut curr_y: u32 = 0;
-        if logo.is_some() {
+        if let Some(logo) = logo {
             trace!("paint logo in at top");
-            let logo: RgbImage =
-                resize(logo.unwrap(), logo_width, logo_height, FilterType::Lanczos3);
             let x = (canvas_width - logo_width) / 2;
             canvas.copy_from(&logo, x, curr_y)?;
+            layout.add(RegionKind::Logo, x, curr_y, logo_width, logo_height);
             curr_y += logo_height;
         }
 
         // give some space between logo & following lines
-        curr_y += base_scale.y as u32;
+        curr_y += space;
 
         // print lines
         trace!("paint lines one by one");
-        let mut index = 0;
-        for line in lines {
-            if line.is_empty() {
-                continue;
+        for line in lines.iter() {
+            let x = (canvas_width - line.layout.width()) / 2;
+            debug!("paint line at x: {}, y: {}", x, curr_y);
+            draw_line(&mut canvas, &mut layout, line, x, curr_y, background, RegionKind::Text);
+
+            curr_y += line.scale.y as u32;
+        }
+
+        // print caption below the space kept for the empty lines
+        curr_y = logo_height + lines_height + caption_gap;
+        for line in caption.iter() {
+            let x = (canvas_width - line.layout.width()) / 2;
+            debug!("paint caption line at x: {}, y: {}", x, curr_y);
+            draw_line(&mut canvas, &mut layout, line, x, curr_y, background, RegionKind::Caption);
+
+            curr_y += line.scale.y as u32;
+        }
+
+        Ok((canvas, layout))
+    }
+
+    /// Logo on the left, lines aligned left beside it & the caption below them, to be turned
+    /// along the long edge, so the strip is at most `max_width` wide and `max_height` high.
+    fn create_strip_canvas(
+        &self,
+        text: &SideText,
+        logo: Option<RgbImage>,
+        standard_padding: u32,
+        max_width: u32,
+        max_height: Option<u32>,
+        background: Rgb<u8>,
+    ) -> Result<(RgbImage, Layout), Box<dyn std::error::Error>> {
+        let mut layout = Layout::new();
+        let base_scale = TextStyle::emphasized().scale(standard_padding);
+        let (logo_width, logo_height) = logo.as_ref().map_or((0, 0), |l| l.dimensions());
+        // give some space between logo & lines
+        let gap = match logo.is_some() && !(text.lines.is_empty() && text.caption.is_empty()) {
+            true => base_scale.y as u32,
+            false => 0,
+        };
+        let max_lines_width = max_width.saturating_sub(logo_width + gap);
+        let lines = fit_lines(
+            &text.lines,
+            standard_padding,
+            Some(max_lines_width),
+            max_height,
+            &self.typography,
+            |_, scale| scale.y as u32,
+        );
+        // a line space between the lines & the caption, which takes the height left
+        let caption_gap = match lines.is_empty() || text.caption.is_empty() {
+            true => 0,
+            false => base_scale.y as u32,
+        };
+        let caption = fit_lines(
+            &text.caption,
+            standard_padding,
+            Some(max_lines_width),
+            max_height.map(|h| {
+                let lines_height = lines.iter().map(|line| line.scale.y as u32).sum::<u32>();
+                h.saturating_sub(lines_height + caption_gap)
+            }),
+            &self.typography,
+            |_, scale| scale.y as u32,
+        );
+
+        let lines_height = lines
+            .iter()
+            .chain(caption.iter())
+            .map(|line| line.scale.y as u32)
+            .sum::<u32>()
+            + caption_gap;
+        let lines_width = lines
+            .iter()
+            .chain(caption.iter())
+            .map(|line| line.layout.width())
+            .max()
+            .unwrap_or(0);
+
+        let canvas_width = logo_width + gap + lines_width;
+        let canvas_height = std::cmp::max(lines_height, logo_height);
+        debug!(
+            "content strip width: {}, height: {}",
+            canvas_width, canvas_height
+        );
+        let mut canvas = create_canvas(canvas_width, canvas_height, background);
+
+        if let Some(logo) = logo {
+            trace!("paint logo in at left");
+            let y = (canvas_height - logo_height) / 2;
+            canvas.copy_from(&logo, 0, y)?;
+            layout.add(RegionKind::Logo, 0, y, logo_width, logo_height);
+        }
+
+        trace!("paint lines one by one");
+        let x = logo_width + gap;
+        let mut curr_y = (canvas_height - lines_height) / 2;
+        for line in lines.iter() {
+            debug!("paint line at x: {}, y: {}", x, curr_y);
+            draw_line(&mut canvas, &mut layout, line, x, curr_y, background, RegionKind::Text);
+
+            curr_y += line.scale.y as u32;
+        }
+        curr_y += caption_gap;
+        for line in caption.iter() {
+            debug!("paint caption line at x: {}, y: {}", x, curr_y);
+            draw_line(&mut canvas, &mut layout, line, x, curr_y, background, RegionKind::Caption);
+
+            curr_y += line.scale.y as u32;
+        }
+
+        Ok((canvas, layout))
+    }
+
+    /// Logo on top, lines as columns of vertical text below it, from right to left, and the
+    /// caption as columns on their left, within the max height. Columns past the max width are
+    /// dropped, the caption first.
+    fn create_vertical_canvas(
+        &self,
+        text: &SideText,
+        logo: Option<RgbImage>,
+        standard_padding: u32,
+        max_width: Option<u32>,
+        max_height: u32,
+        background: Rgb<u8>,
+    ) -> Result<(RgbImage, Layout), Box<dyn std::error::Error>> {
+        let mut layout = Layout::new();
+        let base_scale = TextStyle::emphasized().scale(standard_padding);
+        let (logo_width, logo_height) = logo.as_ref().map_or((0, 0), |l| l.dimensions());
+        // give some space between logo & columns
+        let gap = match logo.is_some() && !(text.lines.is_empty() && text.caption.is_empty()) {
+            true => base_scale.y as u32,
+            false => 0,
+        };
+        let max_columns_height = Some(max_height.saturating_sub(logo_height + gap));
+        let mut columns = fit_columns(
+            &text.lines,
+            standard_padding,
+            max_columns_height,
+            &self.typography,
+        );
+        let mut caption = fit_columns(
+            &text.caption,
+            standard_padding,
+            max_columns_height,
+            &self.typography,
+        );
+        // a fixed side keeps its width, drop the columns past it
+        if let Some(max_width) = max_width {
+            while columns_width(&columns, &caption, base_scale.y as u32) > max_width {
+                if caption.pop().is_none() {
+                    columns.pop();
+                }
             }
+        }
+        // a column space between the lines & the caption
+        let caption_gap = match columns.is_empty() || caption.is_empty() {
+            true => 0,
+            false => base_scale.y as u32,
+        };
 
-            let color = match index {
-                0 => &BLACK,
-                _ => &GRAY,
-            };
-            let font_to_use = match index {
-                0 => font,
-                _ => sub_font.as_ref().unwrap_or(font),
-            };
-
-            let line_width = get_text_scaled_length(&line, font_to_use, base_scale);
-            let x = (canvas_width - line_width) / 2;
-            debug!("paint line: {} at x: {}, y: {}", line, x, curr_y);
-            add_text(
-                &mut canvas,
-                x,
-                curr_y,
-                &line,
-                base_scale,
-                font_to_use,
-                color,
-            );
-
-            curr_y += base_scale.y as u32;
-            index += 1;
+        let all_columns = columns
+            .iter()
+            .map(|column| (column, RegionKind::Text))
+            .chain(caption.iter().map(|column| (column, RegionKind::Caption)))
+            .collect::<Vec<_>>();
+        let columns_width = columns_width(&columns, &caption, base_scale.y as u32);
+        let columns_height = all_columns
+            .iter()
+            .map(|((column, _), _)| column.height())
+            .max()
+            .unwrap_or(0);
+
+        let canvas_width = std::cmp::max(columns_width, logo_width);
+        let canvas_height = logo_height + gap + columns_height;
+        debug!(
+            "content canvas width: {}, height: {}",
+            canvas_width, canvas_height
+        );
+        let mut canvas = create_canvas(canvas_width, canvas_height, background);
+
+        if let Some(logo) = logo {
+            trace!("paint logo in at top");
+            let x = (canvas_width - logo_width) / 2;
+            canvas.copy_from(&logo, x, 0)?;
+            layout.add(RegionKind::Logo, x, 0, logo_width, logo_height);
+        }
+
+        // the first column is the rightmost one
+        trace!("paint columns one by one");
+        let y = logo_height + gap;
+        let mut right = (canvas_width + columns_width) / 2;
+        for (index, ((column, style), kind)) in all_columns.into_iter().enumerate() {
+            if index == columns.len() {
+                right -= caption_gap;
+            }
+            let x = right - column.width();
+            debug!("paint column at x: {}, y: {}", x, y);
+            let color = style.color_on(background);
+            let ink = column.draw(&mut canvas, x as i32, y as i32, &color);
+            if let Some((ink_x, ink_y, ink_width, ink_height)) = ink {
+                layout.add(
+                    kind,
+                    ink_x.max(0) as u32,
+                    ink_y.max(0) as u32,
+                    ink_width,
+                    ink_height,
+                );
+            }
+            right = x;
         }
 
-        Ok(canvas)
+        Ok((canvas, layout))
+    }
+}
+
+/// Width of the columns of the lines & caption side by side, a column space apart.
+fn columns_width(
+    columns: &[(VerticalLayout, TextStyle)],
+    caption: &[(VerticalLayout, TextStyle)],
+    space: u32,
+) -> u32 {
+    let gap = match columns.is_empty() || caption.is_empty() {
+        true => 0,
+        false => space,
+    };
+    columns
+        .iter()
+        .chain(caption.iter())
+        .map(|(column, _)| column.width())
+        .sum::<u32>()
+        + gap
+}
+
+/// Draw a line at (x, y) in its style, recording its ink as a region of the kind.
+fn draw_line(
+    canvas: &mut RgbImage,
+    layout: &mut Layout,
+    line: &FittedLine,
+    x: u32,
+    y: u32,
+    background: Rgb<u8>,
+    kind: RegionKind,
+) {
+    let color = line.style.color_on(background);
+    line.layout.draw(canvas, x as i32, y as i32, &color);
+    add_ink(layout, &line.layout, x, y, kind);
+}
+
+/// Record the ink of a line drawn at (x, y) as a region of the kind.
+fn add_ink(layout: &mut Layout, line: &TextLayout, x: u32, y: u32, kind: RegionKind) {
+    if let Some(ink) = line.ink_bounds() {
+        let ink_x = (x as f32 + ink.min.x).max(0.0) as u32;
+        let ink_y = (y as f32 + ink.min.y).max(0.0) as u32;
+        layout.add(
+            kind,
+            ink_x,
+            ink_y,
+            ink.width().ceil() as u32,
+            ink.height().ceil() as u32,
+        );
     }
 }
 
 impl Painter for DuelPainter {
-    fn paint(
+    fn name(&self) -> &str {
+        match self.diagonal {
+            true => DIAGONAL_PAINTER,
+            false => DUEL_PAINTER,
+        }
+    }
+
+    fn paint_layout(
         &self,
         image: &mut image::RgbImage,
-        exif_info: &crate::entity::ExifInfo,
+        exif_info: &ExifInfo,
+        caption: &Caption,
+        scale: f32,
+        layout: &mut Layout,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let (ori_width, ori_height) = image.dimensions();
         let long_side = std::cmp::max(ori_width, ori_height);
         debug!("origin image width: {}, height: {}", ori_width, ori_height);
 
         // setup padding related variables
-        let standard_padding_ratio = 1.0 / GOLDEN_RATIO / 16.0;
-        let standard_padding = (long_side as f32 * standard_padding_ratio) as u32; // twice of the scaled font height
+        let standard_padding = standard_padding(long_side); // twice of the scaled font height
         let mut trivial_padding: u32 = 0;
         if self.pad_around {
             trivial_padding = standard_padding;
         }
+        let mut padding = Padding::new(
+            trivial_padding,
+            trivial_padding,
+            trivial_padding,
+            trivial_padding,
+        );
+        self.frame.apply(&mut padding, ori_width, ori_height, scale);
+
+        // the main content must not run past the photo, or the offset from it if diagonal
+        let max_content_height = match (self.main_position, self.diagonal) {
+            (Position::LEFT, true) => {
+                (ori_height + padding.bottom).saturating_sub(standard_padding * 2)
+            }
+            (_, true) => (ori_height + padding.top).saturating_sub(standard_padding * 2),
+            (_, false) => ori_height + padding.top + padding.bottom,
+        };
 
-        // prepare font
-        let font_size = standard_padding as f32 * 0.5;
-        let font_scale = PxScale {
-            x: font_size,
-            y: font_size,
+        // a fixed frame keeps the main side, fit the main content into it
+        let (main_side_size, main_side) = match self.main_position {
+            Position::LEFT => (self.frame.left, padding.left),
+            _ => (self.frame.right, padding.right),
+        };
+        let max_content_width = match main_side_size.is_some() && self.frame.fixed {
+            true => Some(main_side.saturating_sub(standard_padding * 2)),
+            false => None,
         };
 
         // create a new main content canvas
-        let main_content_canvas = self.create_main_content_canvas(
+        let (main_content_canvas, main_content_layout) = self.create_main_content_canvas(
             exif_info,
-            &self.font,
-            &self.sub_font,
-            &font_scale,
+            caption,
+            standard_padding,
+            max_content_width,
+            max_content_height,
             WHITE,
         )?;
 
-        // setup padding around the origin image
-        let mut padding = Padding::new(
-            trivial_padding,
-            trivial_padding,
-            trivial_padding,
-            main_content_canvas.width() + standard_padding * 2,
-        );
-        if self.main_position == Position::LEFT {
-            padding = Padding::new(
-                trivial_padding,
-                trivial_padding,
-                main_content_canvas.width() + standard_padding * 2,
-                trivial_padding,
-            );
+        // widen the main side for the main content, the frame may set it even wider
+        let main_side = match self.main_position {
+            Position::LEFT => &mut padding.left,
+            _ => &mut padding.right,
+        };
+        if max_content_width.is_none() {
+            *main_side = (*main_side).max(main_content_canvas.width() + standard_padding * 2);
         }
         add_padding(image, &padding, &WHITE)?;
+        layout.add_padding(ori_width, ori_height, &padding);
 
         // put the main content into the image
-        let mut copy_to_x: u32 = standard_padding;
+        let mut copy_to_x: u32 = padding.left.saturating_sub(main_content_canvas.width()) / 2;
         let copy_to_y: u32;
 
         match (self.main_position, self.diagonal) {
@@ -287,22 +697,20 @@ impl Painter for DuelPainter {
             }
             (Position::LEFT, false) => {
                 // left middle
-                copy_to_y = (image.height() - main_content_canvas.height()) / 2;
+                copy_to_y = image.height().saturating_sub(main_content_canvas.height()) / 2;
             }
             (_, true) => {
                 // right bottom
                 copy_to_x = (image.width() - padding.right)
-                    + (padding.right - main_content_canvas.width()) / 2;
-                copy_to_y = image.height()
-                    - padding.bottom
-                    - standard_padding * 2
-                    - main_content_canvas.height();
+                    + padding.right.saturating_sub(main_content_canvas.width()) / 2;
+                copy_to_y = (image.height() - padding.bottom)
+                    .saturating_sub(standard_padding * 2 + main_content_canvas.height());
             }
             (_, false) => {
                 // right middle
                 copy_to_x = (image.width() - padding.right)
-                    + (padding.right - main_content_canvas.width()) / 2;
-                copy_to_y = (image.height() - main_content_canvas.height()) / 2;
+                    + padding.right.saturating_sub(main_content_canvas.width()) / 2;
+                copy_to_y = image.height().saturating_sub(main_content_canvas.height()) / 2;
             }
         };
 
@@ -316,6 +724,7 @@ impl Painter for DuelPainter {
             copy_to_y
         );
         image.copy_from(&main_content_canvas, copy_to_x, copy_to_y)?;
+        layout.add_at(main_content_layout, copy_to_x, copy_to_y);
 
         Ok(())
     }
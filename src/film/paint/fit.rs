@@ -0,0 +1,362 @@
+use std::str::FromStr;
+
+use ab_glyph::PxScale;
+
+use crate::film::{
+    paint::{
+        constant::{FIT_SHRINK, FIT_TRUNCATE, FIT_WRAP},
+        style::TextStyle,
+        text::{TextLayout, Typography, VerticalLayout},
+    },
+    FontChain,
+};
+
+/// Ellipsis ending truncated lines, if the font has it.
+const ELLIPSIS: &str = "…";
+/// Ellipsis for fonts without `…`.
+const ELLIPSIS_ASCII: &str = "...";
+/// Size of the smallest text in pixels a block is shrunk to, text overflowing at this size is
+/// dropped.
+const MIN_TEXT_SIZE: f32 = 1.0;
+
+/// How lines too long for the space of a painter are fitted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// scale the whole block down until it fits
+    #[default]
+    Shrink,
+    /// cut the long lines, ending them with an ellipsis
+    Truncate,
+    /// break the long lines between words
+    Wrap,
+}
+
+impl FromStr for Fit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            FIT_SHRINK => Ok(Fit::Shrink),
+            FIT_TRUNCATE => Ok(Fit::Truncate),
+            FIT_WRAP => Ok(Fit::Wrap),
+            _ => Err(format!(
+                "unknown fit `{}`, use {}, {} or {}",
+                s, FIT_SHRINK, FIT_TRUNCATE, FIT_WRAP
+            )),
+        }
+    }
+}
+
+/// A line to paint in a block, with its style resolved.
+pub struct StyledLine<'a> {
+    pub text: &'a str,
+    pub font: &'a FontChain,
+    pub style: TextStyle,
+}
+
+/// A line laid out to fit its block, wrapped lines are several of them.
+pub struct FittedLine<'a> {
+    pub layout: TextLayout,
+    pub font: &'a FontChain,
+    pub style: TextStyle,
+    pub scale: PxScale,
+}
+
+/// Lay out the lines of a block within the max width & height, by the fit of the typography.
+///
+/// Lines too wide are truncated or wrapped as set, and the block is shrunk if it's still too
+/// wide or too tall. Lines that overflow even when shrunk to the smallest text are dropped, so
+/// the block always fits. The height of a line is given by `line_height` since painters space
+/// lines differently.
+pub fn fit_lines<'a>(
+    lines: &[StyledLine<'a>],
+    standard_padding: u32,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    typography: &Typography,
+    line_height: impl Fn(&FontChain, PxScale) -> u32,
+) -> Vec<FittedLine<'a>> {
+    let largest = lines
+        .iter()
+        .map(|l| l.style.scale(standard_padding).y)
+        .fold(0.0, f32::max);
+    let layout = |factor: f32| {
+        let mut fitted = Vec::new();
+        for line in lines {
+            let base = line.style.scale(standard_padding);
+            let scale = PxScale {
+                x: base.x * factor,
+                y: base.y * factor,
+            };
+            let pieces = match (typography.fit, max_width) {
+                (Fit::Truncate, Some(max)) => vec![truncate(line, scale, max, typography)],
+                (Fit::Wrap, Some(max)) => wrap(line, scale, max, typography),
+                _ => vec![line.style.layout(line.text, line.font, scale, typography)],
+            };
+            fitted.extend(pieces.into_iter().map(|layout| FittedLine {
+                layout,
+                font: line.font,
+                style: line.style,
+                scale,
+            }));
+        }
+        fitted
+    };
+    // lines are one below another
+    let measure = |block: &[FittedLine]| {
+        let width = block.iter().map(|l| l.layout.width()).max().unwrap_or(0);
+        let height = block.iter().map(|l| line_height(l.font, l.scale)).sum();
+        (width, height)
+    };
+    shrink_to_fit(largest, max_width, max_height, layout, measure)
+}
+
+/// Lay out vertical columns within the max height, shrinking them since they cannot be
+/// truncated or wrapped. Columns too tall even when shrunk to the smallest text are dropped.
+pub fn fit_columns(
+    lines: &[StyledLine],
+    standard_padding: u32,
+    max_height: Option<u32>,
+    typography: &Typography,
+) -> Vec<(VerticalLayout, TextStyle)> {
+    let largest = lines
+        .iter()
+        .map(|l| l.style.scale(standard_padding).y)
+        .fold(0.0, f32::max);
+    let layout = |factor: f32| {
+        lines
+            .iter()
+            .map(|line| {
+                let base = line.style.scale(standard_padding);
+                let scale = PxScale {
+                    x: base.x * factor,
+                    y: base.y * factor,
+                };
+                let column = line.style.vertical_layout(
+                    line.text,
+                    line.font,
+                    scale,
+                    scale.y as u32,
+                    typography,
+                );
+                (column, line.style)
+            })
+            .collect::<Vec<_>>()
+    };
+    // columns are side by side
+    let measure = |block: &[(VerticalLayout, TextStyle)]| {
+        let width = block.iter().map(|(c, _)| c.width()).sum();
+        let height = block.iter().map(|(c, _)| c.height()).max().unwrap_or(0);
+        (width, height)
+    };
+    shrink_to_fit(largest, None, max_height, layout, measure)
+}
+
+/// Shrink a block laid out at a factor of its size until it fits within the max width & height.
+///
+/// The block is shrunk by the ratio it overflows each time, until its largest text would be
+/// smaller than `MIN_TEXT_SIZE`. Then it's laid out at that size, and the items overflowing by
+/// themselves are dropped, along with the last ones while the block still overflows.
+fn shrink_to_fit<T>(
+    largest_size: f32,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    layout: impl Fn(f32) -> Vec<T>,
+    measure: impl Fn(&[T]) -> (u32, u32),
+) -> Vec<T> {
+    // ratio to shrink the block by to fit, 1 if it fits
+    let overflow = |block: &[T]| {
+        let (width, height) = measure(block);
+        let mut ratio: f32 = 1.0;
+        if let Some(max) = max_width.filter(|max| width > *max) {
+            ratio = ratio.min(max as f32 / width as f32);
+        }
+        if let Some(max) = max_height.filter(|max| height > *max) {
+            ratio = ratio.min(max as f32 / height as f32);
+        }
+        ratio
+    };
+
+    let mut factor: f32 = 1.0;
+    loop {
+        let block = layout(factor);
+        let ratio = overflow(&block);
+        if ratio >= 1.0 {
+            return block;
+        }
+        // a bit more than the ratio, as sizes are rounded up
+        let next = factor * ratio * 0.98;
+        if largest_size * next >= MIN_TEXT_SIZE {
+            factor = next;
+            continue;
+        }
+
+        let smallest = (MIN_TEXT_SIZE / largest_size).min(factor);
+        let mut block = layout(smallest);
+        block.retain(|item| overflow(std::slice::from_ref(item)) >= 1.0);
+        while overflow(&block) < 1.0 {
+            block.pop();
+        }
+        return block;
+    }
+}
+
+/// Cut the line to the max width, ending it with an ellipsis.
+fn truncate(
+    line: &StyledLine,
+    scale: PxScale,
+    max_width: u32,
+    typography: &Typography,
+) -> TextLayout {
+    let layout = line.style.layout(line.text, line.font, scale, typography);
+    if layout.width() <= max_width {
+        return layout;
+    }
+
+    let ellipsis = match line.font.face_for('…') {
+        Some(_) => ELLIPSIS,
+        None => ELLIPSIS_ASCII,
+    };
+    let chars: Vec<char> = line.text.chars().collect();
+    let cut = |count: usize| {
+        let head: String = chars[..count].iter().collect();
+        format!("{}{}", head.trim_end(), ellipsis)
+    };
+
+    // find the most chars to keep
+    let (mut low, mut high) = (0, chars.len() - 1);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        let width = line
+            .style
+            .layout(&cut(mid), line.font, scale, typography)
+            .width();
+        match width <= max_width {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+    line.style.layout(&cut(low), line.font, scale, typography)
+}
+
+/// Break the line between words into lines within the max width, words too long by themselves
+/// are broken between chars.
+fn wrap(
+    line: &StyledLine,
+    scale: PxScale,
+    max_width: u32,
+    typography: &Typography,
+) -> Vec<TextLayout> {
+    let layout = line.style.layout(line.text, line.font, scale, typography);
+    if layout.width() <= max_width {
+        return vec![layout];
+    }
+
+    let fits = |text: &str| {
+        line.style
+            .layout(text, line.font, scale, typography)
+            .width()
+            <= max_width
+    };
+    let mut texts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in line.text.split_whitespace() {
+        let candidate = match current.is_empty() {
+            true => word.to_string(),
+            false => format!("{} {}", current, word),
+        };
+        if fits(&candidate) {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            texts.push(std::mem::take(&mut current));
+        }
+        // break the word itself if needed
+        for c in word.chars() {
+            let candidate = format!("{}{}", current, c);
+            if !current.is_empty() && !fits(&candidate) {
+                texts.push(std::mem::take(&mut current));
+                current.push(c);
+            } else {
+                current = candidate;
+            }
+        }
+    }
+    if !current.is_empty() {
+        texts.push(current);
+    }
+
+    texts
+        .iter()
+        .map(|text| line.style.layout(text, line.font, scale, typography))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lay out lines of text at a factor, as widths & heights rounded up like glyphs, with the
+    /// fixed overhead of tracking & padding which shrinking the text does not reduce.
+    fn lines(sizes: &[(f32, f32)], overhead: u32) -> impl Fn(f32) -> Vec<(u32, u32)> + '_ {
+        move |factor| {
+            sizes
+                .iter()
+                .map(|(w, h)| ((w * factor).ceil() as u32 + overhead, (h * factor).ceil() as u32))
+                .collect()
+        }
+    }
+
+    fn stacked(block: &[(u32, u32)]) -> (u32, u32) {
+        let width = block.iter().map(|(w, _)| *w).max().unwrap_or(0);
+        (width, block.iter().map(|(_, h)| *h).sum())
+    }
+
+    #[test]
+    fn block_that_fits_keeps_its_size() {
+        let sizes = [(300.0, 40.0), (200.0, 30.0)];
+        let block = shrink_to_fit(40.0, Some(500), Some(100), lines(&sizes, 0), stacked);
+        assert_eq!(block, vec![(300, 40), (200, 30)]);
+    }
+
+    #[test]
+    fn long_text_is_shrunk_to_fit() {
+        let sizes = [(20000.0, 40.0), (200.0, 30.0)];
+        let block = shrink_to_fit(40.0, Some(500), Some(100), lines(&sizes, 0), stacked);
+        assert_eq!(block.len(), 2);
+        assert!(stacked(&block).0 <= 500);
+    }
+
+    #[test]
+    fn shrinking_goes_on_past_the_overhead() {
+        // every shrink barely helps as most of the width is the overhead
+        let sizes = [(100.0, 40.0)];
+        let block = shrink_to_fit(40.0, Some(50), None, lines(&sizes, 45), stacked);
+        assert_eq!(block.len(), 1);
+        assert!(stacked(&block).0 <= 50);
+    }
+
+    #[test]
+    fn text_overflowing_at_the_smallest_size_is_dropped() {
+        let sizes = [(100.0, 40.0), (100.0, 40.0)];
+        let block = shrink_to_fit(40.0, Some(50), None, lines(&sizes, 60), stacked);
+        assert!(block.is_empty());
+    }
+
+    #[test]
+    fn zero_height_fits_nothing() {
+        let sizes = [(300.0, 40.0), (200.0, 30.0)];
+        let block = shrink_to_fit(40.0, None, Some(0), lines(&sizes, 0), stacked);
+        assert!(block.is_empty());
+    }
+
+    #[test]
+    fn lines_are_dropped_from_the_end_until_the_block_fits() {
+        // each line is 1px high at least, so only 2 of them fit in 2px
+        let sizes = [(10.0, 40.0), (10.0, 40.0), (10.0, 40.0)];
+        let block = shrink_to_fit(40.0, None, Some(2), lines(&sizes, 0), stacked);
+        assert_eq!(block.len(), 2);
+        assert!(stacked(&block).1 <= 2);
+    }
+}
@@ -0,0 +1,237 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entity::Padding,
+    film::paint::constant::{
+        FRAME_INSTAX_MINI, FRAME_INSTAX_WIDE, FRAME_POLAROID_600, FRAME_SX_70, GOLDEN_RATIO,
+    },
+};
+
+/// Ratio of the standard padding to the long side of the photo, which sizes the text as well.
+const STANDARD_PADDING_RATIO: f32 = 1.0 / GOLDEN_RATIO / 16.0;
+
+/// Frames of real instant films, by the short side of the image window and the borders around
+/// it in mm: top, bottom, left & right, with the thick border at the bottom.
+pub const FRAME_PRESETS: [(&str, f32, [f32; 4]); 4] = [
+    (FRAME_INSTAX_MINI, 46.0, [6.5, 17.5, 4.0, 4.0]),
+    (FRAME_INSTAX_WIDE, 62.0, [7.0, 17.0, 4.5, 4.5]),
+    (FRAME_POLAROID_600, 79.0, [6.0, 22.0, 4.5, 4.5]),
+    (FRAME_SX_70, 77.0, [6.5, 23.5, 4.5, 4.5]),
+];
+
+/// Standard padding of the painters for the long side of the photo, twice of the scaled font
+/// height.
+pub fn standard_padding(long_side: u32) -> u32 {
+    (long_side as f32 * STANDARD_PADDING_RATIO) as u32
+}
+
+/// Size of a padding, relative to a side of the photo or in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaddingSize {
+    /// ratio of the long side of the photo
+    Long(f32),
+    /// ratio of the short side of the photo
+    Short(f32),
+    Pixels(u32),
+}
+
+impl PaddingSize {
+    /// Size in pixels for a photo of the long & short side, pixel sizes follow the scale of a
+    /// downscaled photo like the ratios follow its sides.
+    pub fn pixels(&self, long_side: u32, short_side: u32, scale: f32) -> u32 {
+        match self {
+            PaddingSize::Long(r) => (long_side as f32 * r).round() as u32,
+            PaddingSize::Short(r) => (short_side as f32 * r).round() as u32,
+            PaddingSize::Pixels(p) => (*p as f32 * scale).round() as u32,
+        }
+    }
+}
+
+impl FromStr for PaddingSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let invalid = || {
+            format!(
+                "invalid padding size `{}`, use a ratio of the long or short side from 0 to 1 like `0.05long` or `0.08short`, or pixels like `120px`",
+                s
+            )
+        };
+        let ratio = |r: &str| match r.parse::<f32>() {
+            Ok(r) if (0.0..=1.0).contains(&r) => Ok(r),
+            _ => Err(invalid()),
+        };
+        if let Some(r) = s.strip_suffix("long") {
+            Ok(PaddingSize::Long(ratio(r)?))
+        } else if let Some(r) = s.strip_suffix("short") {
+            Ok(PaddingSize::Short(ratio(r)?))
+        } else if let Some(p) = s.strip_suffix("px") {
+            p.parse().map(PaddingSize::Pixels).map_err(|_| invalid())
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+impl Display for PaddingSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaddingSize::Long(r) => write!(f, "{}long", r),
+            PaddingSize::Short(r) => write!(f, "{}short", r),
+            PaddingSize::Pixels(p) => write!(f, "{}px", p),
+        }
+    }
+}
+
+/// Paddings of the sides set by the user, overriding the ones of the painter.
+///
+/// It's written like `instax-mini,bottom=0.3short,sides=24px`, a preset of a real instant film
+/// followed by the sides to set, each is optional:
+///
+/// - a preset: `instax-mini`, `instax-wide`, `polaroid-600` or `sx-70`, setting all the sides
+/// - `top`, `bottom`, `left`, `right`: a side, `sides` for left & right and `all` for all of them
+/// - `fixed`: keep the sides as set, the side text of `duel` & `diagonal` is shrunk into its side
+///   instead of widening it
+///
+/// Sizes are a ratio of the long or short side of the photo like `0.05long` or `0.08short`, or
+/// pixels like `120px`. The side painted with the EXIF info is never smaller than it needs, unless
+/// the frame is fixed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Frame {
+    pub top: Option<PaddingSize>,
+    pub bottom: Option<PaddingSize>,
+    pub left: Option<PaddingSize>,
+    pub right: Option<PaddingSize>,
+    /// fit the EXIF info into its side instead of widening the side
+    pub fixed: bool,
+}
+
+impl Frame {
+    /// Frame of a real instant film by its preset name.
+    pub fn preset(name: &str) -> Option<Frame> {
+        FRAME_PRESETS
+            .iter()
+            .find(|(n, _, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, short_side, sides)| {
+                let [top, bottom, left, right] =
+                    sides.map(|mm| Some(PaddingSize::Short(mm / short_side)));
+                Frame {
+                    top,
+                    bottom,
+                    left,
+                    right,
+                    fixed: false,
+                }
+            })
+    }
+
+    /// Override the sides of the padding set by the frame, for a photo of the width & height,
+    /// downscaled by the scale for previews.
+    pub fn apply(&self, padding: &mut Padding, width: u32, height: u32, scale: f32) {
+        let (long_side, short_side) = (width.max(height), width.min(height));
+        let sides = [
+            (self.top, &mut padding.top),
+            (self.bottom, &mut padding.bottom),
+            (self.left, &mut padding.left),
+            (self.right, &mut padding.right),
+        ];
+        for (size, side) in sides {
+            if let Some(size) = size {
+                *side = size.pixels(long_side, short_side, scale);
+            }
+        }
+    }
+}
+
+impl FromStr for Frame {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut frame = Frame::default();
+        for item in s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let (key, value) = match item.split_once('=') {
+                Some((key, value)) => (key.trim(), value.parse::<PaddingSize>()?),
+                None if item.eq_ignore_ascii_case("fixed") => {
+                    frame.fixed = true;
+                    continue;
+                }
+                None => {
+                    frame = match Frame::preset(item) {
+                        Some(f) => Frame {
+                            fixed: frame.fixed,
+                            ..f
+                        },
+                        None => {
+                            return Err(format!(
+                                "unknown frame preset `{}`, use one of [{}]",
+                                item,
+                                FRAME_PRESETS.map(|(name, _, _)| name).join(", ")
+                            ))
+                        }
+                    };
+                    continue;
+                }
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "top" => frame.top = Some(value),
+                "bottom" => frame.bottom = Some(value),
+                "left" => frame.left = Some(value),
+                "right" => frame.right = Some(value),
+                "sides" => {
+                    frame.left = Some(value);
+                    frame.right = Some(value);
+                }
+                "all" => {
+                    frame.top = Some(value);
+                    frame.bottom = Some(value);
+                    frame.left = Some(value);
+                    frame.right = Some(value);
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown frame side `{}`, use top, bottom, left, right, sides or all",
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(frame)
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sides = [
+            ("top", self.top),
+            ("bottom", self.bottom),
+            ("left", self.left),
+            ("right", self.right),
+        ];
+        let mut pairs = sides
+            .iter()
+            .filter_map(|(key, size)| size.map(|s| format!("{}={}", key, s)))
+            .collect::<Vec<_>>();
+        if self.fixed {
+            pairs.push("fixed".to_string());
+        }
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl TryFrom<String> for Frame {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Frame> for String {
+    fn from(value: Frame) -> Self {
+        value.to_string()
+    }
+}
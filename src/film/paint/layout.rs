@@ -0,0 +1,205 @@
+use image::{Rgb, RgbImage};
+use imageproc::{drawing::draw_hollow_rect_mut, rect::Rect};
+
+use crate::entity::Padding;
+
+/// What a region of the painted image holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    Photo,
+    Padding,
+    Text,
+    Logo,
+    Delimiter,
+    Caption,
+}
+
+impl RegionKind {
+    /// Color of the box drawn around regions of this kind.
+    pub fn color(&self) -> Rgb<u8> {
+        match self {
+            RegionKind::Photo => Rgb([0, 160, 0]),
+            RegionKind::Padding => Rgb([0, 96, 255]),
+            RegionKind::Text => Rgb([255, 0, 0]),
+            RegionKind::Logo => Rgb([255, 0, 255]),
+            RegionKind::Delimiter => Rgb([255, 160, 0]),
+            RegionKind::Caption => Rgb([0, 192, 192]),
+        }
+    }
+}
+
+/// A rectangle of the painted image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub kind: RegionKind,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Regions a painter paints, used to review layouts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    regions: Vec<Region>,
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Layout {
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    pub fn add(&mut self, kind: RegionKind, x: u32, y: u32, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.regions.push(Region {
+            kind,
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    /// Add the regions of a canvas that is copied to (x, y).
+    pub fn add_at(&mut self, other: Layout, x: u32, y: u32) {
+        for region in other.regions {
+            self.add(
+                region.kind,
+                region.x + x,
+                region.y + y,
+                region.width,
+                region.height,
+            );
+        }
+    }
+
+    /// Clip the regions to a canvas of the size, dropping the ones outside of it.
+    ///
+    /// Ink of glyphs may reach out of the canvas, e.g. by a negative left side bearing.
+    pub fn clip(&mut self, width: u32, height: u32) {
+        self.regions.retain_mut(|region| {
+            let right = std::cmp::min(region.x.saturating_add(region.width), width);
+            let bottom = std::cmp::min(region.y.saturating_add(region.height), height);
+            region.width = right.saturating_sub(region.x);
+            region.height = bottom.saturating_sub(region.y);
+            region.width > 0 && region.height > 0
+        });
+    }
+
+    /// Turn the regions of a canvas 90° clockwise, along with the canvas.
+    pub fn rotate90(&mut self, width: u32, height: u32) {
+        self.clip(width, height);
+        for region in self.regions.iter_mut() {
+            *region = Region {
+                kind: region.kind,
+                x: height - region.y - region.height,
+                y: region.x,
+                width: region.height,
+                height: region.width,
+            };
+        }
+    }
+
+    /// Turn the regions of a canvas 90° counterclockwise, along with the canvas.
+    pub fn rotate270(&mut self, width: u32, height: u32) {
+        self.clip(width, height);
+        for region in self.regions.iter_mut() {
+            *region = Region {
+                kind: region.kind,
+                x: region.y,
+                y: width - region.x - region.width,
+                width: region.height,
+                height: region.width,
+            };
+        }
+    }
+
+    /// Add the photo of the given size and the paddings around it.
+    pub fn add_padding(&mut self, width: u32, height: u32, padding: &Padding) {
+        let full_width = width + padding.left + padding.right;
+        self.add(RegionKind::Photo, padding.left, padding.top, width, height);
+        self.add(RegionKind::Padding, 0, 0, full_width, padding.top);
+        self.add(
+            RegionKind::Padding,
+            0,
+            padding.top + height,
+            full_width,
+            padding.bottom,
+        );
+        self.add(RegionKind::Padding, 0, padding.top, padding.left, height);
+        self.add(
+            RegionKind::Padding,
+            padding.left + width,
+            padding.top,
+            padding.right,
+            height,
+        );
+    }
+}
+
+/// Draw a box around each region of the layout, colored by its kind.
+pub fn draw_layout(image: &mut RgbImage, layout: &Layout) {
+    let thickness = std::cmp::max(std::cmp::max(image.width(), image.height()) / 512, 1);
+    for region in layout.regions() {
+        for i in 0..thickness {
+            if region.width <= i * 2 || region.height <= i * 2 {
+                break;
+            }
+            let rect = Rect::at((region.x + i) as i32, (region.y + i) as i32)
+                .of_size(region.width - i * 2, region.height - i * 2);
+            draw_hollow_rect_mut(image, rect, region.kind.color());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(regions: &[(u32, u32, u32, u32)]) -> Layout {
+        let mut layout = Layout::new();
+        for &(x, y, width, height) in regions {
+            layout.add(RegionKind::Text, x, y, width, height);
+        }
+        layout
+    }
+
+    fn rects(layout: &Layout) -> Vec<(u32, u32, u32, u32)> {
+        layout
+            .regions()
+            .iter()
+            .map(|r| (r.x, r.y, r.width, r.height))
+            .collect()
+    }
+
+    #[test]
+    fn rotate_regions() {
+        let mut clockwise = layout(&[(10, 20, 30, 40)]);
+        clockwise.rotate90(100, 80);
+        assert_eq!(rects(&clockwise), vec![(20, 10, 40, 30)]);
+
+        let mut counterclockwise = layout(&[(10, 20, 30, 40)]);
+        counterclockwise.rotate270(100, 80);
+        assert_eq!(rects(&counterclockwise), vec![(20, 60, 40, 30)]);
+    }
+
+    #[test]
+    fn rotate_regions_out_of_canvas() {
+        // ink below the descent & past the advance, and a region fully outside
+        let mut layout = layout(&[(90, 70, 20, 20), (120, 0, 10, 10)]);
+        layout.rotate90(100, 80);
+        assert_eq!(rects(&layout), vec![(0, 90, 10, 10)]);
+
+        let mut layout = self::layout(&[(90, 70, 20, 20)]);
+        layout.rotate270(100, 80);
+        assert_eq!(rects(&layout), vec![(70, 0, 10, 10)]);
+    }
+}
@@ -1,25 +1,80 @@
 pub mod blank;
+pub mod caption;
 pub mod constant;
 pub mod duel;
+pub mod fit;
+pub mod frame;
+pub mod layout;
+pub mod style;
+pub mod text;
 pub mod triangular;
 
 use std::{error::Error, sync::Arc};
 
-use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
 use blank::BlankPainter;
 use constant::{BLANK_PAINTER, DIAGONAL_PAINTER, DUEL_PAINTER, TRIANGLULAR_PAINTER};
 use duel::DuelPainter;
-use image::{GenericImage, ImageBuffer, Rgb, RgbImage};
-use imageproc::drawing::draw_text_mut;
+use fit::FittedLine;
+use frame::Frame;
+use image::{
+    imageops::{resize, FilterType},
+    GenericImage, ImageBuffer, Rgb, RgbImage,
+};
+use layout::Layout;
+use log::debug;
+use text::Typography;
 use triangular::TriangularPainter;
 
 use crate::{
-    entity::{ExifInfo, Padding, Position},
-    film::LogoCache,
+    entity::{Caption, ExifInfo, Padding, Position},
+    film::{FontSet, LogoCache},
 };
 
+/// Names & descriptions of all the painters.
+pub const PAINTERS: [(&str, &str); 4] = [
+    (
+        TRIANGLULAR_PAINTER,
+        "a traditional instant film layout with EXIF information displayed below, the default one",
+    ),
+    (
+        BLANK_PAINTER,
+        "a raw instant film with only blank paddings, no EXIF information added but the caption",
+    ),
+    (
+        DUEL_PAINTER,
+        "EXIF information with logo displayed on left or right",
+    ),
+    (
+        DIAGONAL_PAINTER,
+        "like `duel` but displays EXIF information on top-left or bottom-right",
+    ),
+];
+
 pub trait Painter: Send + Sync {
-    fn paint(&self, image: &mut RgbImage, exif_info: &ExifInfo) -> Result<(), Box<dyn Error>>;
+    /// Name of the painter, e.g. `triangular`.
+    fn name(&self) -> &str;
+
+    /// Paint on the image with the caption, recording the regions painted into the layout.
+    ///
+    /// The `scale` is the size of the image relative to the photo, below 1 if it's downscaled for
+    /// previews, which the sizes in pixels set by the user follow.
+    fn paint_layout(
+        &self,
+        image: &mut RgbImage,
+        exif_info: &ExifInfo,
+        caption: &Caption,
+        scale: f32,
+        layout: &mut Layout,
+    ) -> Result<(), Box<dyn Error>>;
+
+    fn paint(
+        &self,
+        image: &mut RgbImage,
+        exif_info: &ExifInfo,
+        caption: &Caption,
+    ) -> Result<(), Box<dyn Error>> {
+        self.paint_layout(image, exif_info, caption, 1.0, &mut Layout::new())
+    }
 }
 
 /// Add padding around the image.
@@ -60,45 +115,15 @@ fn add_padding(
     Ok(())
 }
 
-/// Add a text to image.
-///
-/// This function will add a text on (x,y) with font settings to the given image.
-///
-/// # Examples
-///
-/// ```
-/// add_text(image, "hello", color, font_scale, &font, x y);
-/// ```
-///
-/// #Arguments
-/// - `image`: a RgbImage to add the text
-/// - `x`: x-coordinate where the text starts
-/// - `y`: y-coordinate where the text starts
-/// - `text`: content of the text
-/// - `scale`: font scale to control the text size
-/// - `font`: font for the text
-/// - `color`: color of the text
-fn add_text(
-    image: &mut RgbImage,
-    x: u32,
-    y: u32,
-    text: &str,
-    scale: &PxScale,
-    font: &FontVec,
-    color: &Rgb<u8>,
-) {
-    draw_text_mut(image, *color, x as i32, y as i32, *scale, &font, text);
-}
-
 /// Add a vertical line to image.
 ///
-/// This function will draw a vertical line from (x,y) to (x,y+height) on a given image,
-/// thickness and gradient color effects are supported.
+/// This function will draw a solid vertical line from (x,y) to (x,y+height) on a given image,
+/// centered on x with the given thickness.
 ///
 /// # Examples
 ///
 /// ```
-/// add_vertical_line(image, x, y, 50, 10, black, gray);
+/// add_vertical_line(image, x, y, 50, 10, gray);
 /// ```
 ///
 /// # Arguments
@@ -107,97 +132,99 @@ fn add_text(
 /// - `y`: y-coordinate where the line starts
 /// - `length`: length of the the line
 /// - `thickness`: thickness of the line
-/// - `start_color`: starting color of the gradient
-/// - `end_color`: ending color of the gradient
+/// - `color`: color of the line
 fn add_vertical_line(
     image: &mut RgbImage,
     x: u32,
     y: u32,
     length: u32,
     thickness: u32,
-    start_color: &Rgb<u8>,
-    end_color: &Rgb<u8>,
+    color: &Rgb<u8>,
 ) {
     let half_width = thickness / 2;
-    for y in y..(y + length) {
-        for x in x.saturating_sub(half_width)..=x + half_width {
-            // Calculate the distance from the center of the line
-            let distance = ((x as i32 - x as i32).abs()) as f32 / half_width as f32;
-
-            // Interpolate between start_color and end_color based on the distance
-            let r =
-                (start_color[0] as f32 * (1.0 - distance) + end_color[0] as f32 * distance) as u8;
-            let g =
-                (start_color[1] as f32 * (1.0 - distance) + end_color[1] as f32 * distance) as u8;
-            let b =
-                (start_color[2] as f32 * (1.0 - distance) + end_color[2] as f32 * distance) as u8;
-
-            // Set the pixel color at (x, y) to the calculated color
-            if x < image.width() && y < image.height() {
-                image.put_pixel(x, y, Rgb([r, g, b]));
+    for py in y..(y + length) {
+        for px in x.saturating_sub(half_width)..=x + half_width {
+            if px < image.width() && py < image.height() {
+                image.put_pixel(px, py, *color);
             }
         }
     }
 }
 
-/// Calculate the pixel width of a text rendered.
-///
-/// # Examples
-/// ```
-/// let actual_width = calculate_text_width("hello", font, scale);
-/// ```
-///
-/// # Arguments
-/// - `text`: text content
-/// - `font`: font to use
-/// - `scale`: scale of the font
+/// Text lines the painter would paint for the EXIF info, without the empty ones.
 ///
-/// # Returns
-/// - Returns the width of the final plotted text in pixel.
-fn get_text_scaled_length(text: &str, font: &impl Font, scale: &PxScale) -> u32 {
-    let scaled_font = font.as_scaled(*scale);
-    text.chars()
-        .map(|c| scaled_font.h_advance(font.glyph_id(c)))
-        .sum::<f32>() as u32
+/// The lines come from the same `get_lines` the painters paint from, and are skipped the same
+/// way they skip them.
+pub fn get_painter_lines(painter_name: &str, exif_info: &ExifInfo) -> Vec<String> {
+    let mut lines = match painter_name.to_lowercase().as_str() {
+        TRIANGLULAR_PAINTER => TriangularPainter::get_lines(exif_info),
+        DUEL_PAINTER | DIAGONAL_PAINTER => DuelPainter::get_lines(exif_info),
+        _ => Vec::new(),
+    };
+    lines.retain(|line| !line.is_empty());
+    lines
+}
+
+/// Side the painter paints the EXIF info in, where the thick border of a film goes.
+pub fn main_side(painter_type: Option<&str>, main_position: Option<Position>) -> Position {
+    match painter_type.map(|pt| pt.to_lowercase()).as_deref() {
+        Some(DUEL_PAINTER) | Some(DIAGONAL_PAINTER) => match main_position {
+            Some(Position::LEFT) => Position::LEFT,
+            _ => Position::RIGHT,
+        },
+        Some(TRIANGLULAR_PAINTER) => match main_position {
+            Some(Position::TOP) => Position::TOP,
+            _ => Position::BOTTOM,
+        },
+        _ => Position::BOTTOM,
+    }
 }
 
 pub fn create_painter(
     painter_type: Option<String>,
-    font: Arc<FontVec>,
-    sub_font: Arc<Option<FontVec>>,
+    fonts: FontSet,
     cache: Arc<LogoCache>,
     main_position: Option<Position>,
     pad_around: bool,
+    typography: Typography,
+    frame: Frame,
 ) -> Box<dyn Painter> {
     match painter_type {
         Some(pt) => match pt.to_lowercase().as_str() {
             TRIANGLULAR_PAINTER => Box::new(TriangularPainter::new(
                 cache,
-                font,
-                sub_font,
+                fonts,
                 main_position.unwrap_or(Position::BOTTOM),
                 pad_around,
+                typography,
+                frame,
             )),
-            BLANK_PAINTER => Box::new(BlankPainter::new(pad_around)),
+            BLANK_PAINTER => Box::new(BlankPainter::new(fonts, pad_around, typography, frame)),
             DUEL_PAINTER => Box::new(DuelPainter::new(
                 cache,
-                font,
-                sub_font,
+                fonts,
                 main_position.unwrap_or(Position::RIGHT),
                 false,
                 pad_around,
+                typography,
+                frame,
             )),
             DIAGONAL_PAINTER => Box::new(DuelPainter::new(
                 cache,
-                font,
-                sub_font,
+                fonts,
                 main_position.unwrap_or(Position::RIGHT),
                 true,
                 pad_around,
+                typography,
+                frame,
+            )),
+            _ => Box::new(TriangularPainter::new_normal(
+                cache, fonts, typography, frame,
             )),
-            _ => Box::new(TriangularPainter::new_normal(cache, font)),
         },
-        None => Box::new(TriangularPainter::new_normal(cache, font)),
+        None => Box::new(TriangularPainter::new_normal(
+            cache, fonts, typography, frame,
+        )),
     }
 }
 
@@ -210,3 +237,148 @@ pub fn create_canvas(width: u32, height: u32, color: Rgb<u8>) -> RgbImage {
     }
     canvas
 }
+
+/// Shrink the logo into the max width & height keeping its ratio, none if there is no room for
+/// it at all.
+pub fn fit_logo(logo: RgbImage, max_width: Option<u32>, max_height: Option<u32>) -> Option<RgbImage> {
+    let (width, height) = logo.dimensions();
+    let (max_width, max_height) = (max_width.unwrap_or(width), max_height.unwrap_or(height));
+    if width <= max_width && height <= max_height {
+        return Some(logo);
+    }
+    let ratio = f64::min(
+        max_width as f64 / width as f64,
+        max_height as f64 / height as f64,
+    );
+    let (new_width, new_height) = (
+        (width as f64 * ratio) as u32,
+        (height as f64 * ratio) as u32,
+    );
+    if new_width == 0 || new_height == 0 {
+        debug!("no room for the logo of {}x{}, drop it", width, height);
+        return None;
+    }
+    debug!("shrink logo from {}x{} to {}x{}", width, height, new_width, new_height);
+    Some(resize(&logo, new_width, new_height, FilterType::Lanczos3))
+}
+
+/// Paint fitted lines one below another on a canvas just holding them.
+///
+/// Lines are aligned to the left, right or centered by `align`, and spaced by `line_height`.
+pub fn create_lines_canvas(
+    lines: &[FittedLine],
+    align: Position,
+    line_height: impl Fn(&FittedLine) -> u32,
+    background: Rgb<u8>,
+) -> RgbImage {
+    // quick return
+    if lines.is_empty() {
+        return create_canvas(1, 1, background);
+    }
+
+    // calculate the size of canvas
+    let line_heights: Vec<u32> = lines.iter().map(&line_height).collect();
+    let height = std::cmp::max(line_heights.iter().sum(), 1);
+    let width = lines
+        .iter()
+        .map(|line| line.layout.width())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    // print lines on it
+    let mut canvas = create_canvas(width, height, background);
+    let mut y: u32 = 0;
+    for (line, line_height) in lines.iter().zip(line_heights) {
+        // align the ink of the line to the right if needed
+        let x = match align {
+            Position::RIGHT => width - line.layout.right_edge(),
+            Position::MIDDLE => (width - line.layout.width()) / 2,
+            _ => 0,
+        };
+        let color = line.style.color_on(background);
+        line.layout.draw(&mut canvas, x as i32, y as i32, &color);
+        y += line_height;
+    }
+
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        entity::info::fixture::exif_info,
+        film::{font::fixture, logo::fixture::wide_logo_cache},
+    };
+
+    use super::{fit::Fit, text::Orientation, *};
+
+    #[test]
+    fn narrow_portrait_with_wide_logo_is_painted_by_every_painter() {
+        let (fonts, cache) = (fixture::font_set(), wide_logo_cache());
+        let caption = Caption {
+            title: Some("Morning Tide".to_string()),
+            location: Some("Kamakura".to_string()),
+            signature: None,
+        };
+        let photo = resize(
+            &create_canvas(4, 100, Rgb([40, 80, 120])),
+            40,
+            1000,
+            FilterType::Nearest,
+        );
+        let positions = [
+            None,
+            Some(Position::TOP),
+            Some(Position::BOTTOM),
+            Some(Position::LEFT),
+            Some(Position::RIGHT),
+        ];
+        let fits = [Fit::Shrink, Fit::Truncate, Fit::Wrap];
+        for (painter, _) in PAINTERS {
+            // only the side text of `duel` & `diagonal` is oriented
+            let orientations = match painter {
+                DUEL_PAINTER | DIAGONAL_PAINTER => vec![
+                    Orientation::Horizontal,
+                    Orientation::Rotated,
+                    Orientation::Vertical,
+                ],
+                _ => vec![Orientation::Horizontal],
+            };
+            for position in positions {
+                for fit in fits {
+                    for side_text in orientations.iter().copied() {
+                        let typography = Typography {
+                            fit,
+                            side_text,
+                            ..Typography::default()
+                        };
+                        let painter = create_painter(
+                            Some(painter.to_string()),
+                            fonts.clone(),
+                            cache.clone(),
+                            position,
+                            false,
+                            typography,
+                            Frame::default(),
+                        );
+                        let mut image = photo.clone();
+                        let mut layout = Layout::new();
+                        let painted =
+                            painter.paint_layout(&mut image, &exif_info(), &caption, 1.0, &mut layout);
+                        assert!(
+                            painted.is_ok(),
+                            "{} at {:?}, {:?}, {:?}: {:?}",
+                            painter.name(),
+                            position,
+                            fit,
+                            side_text,
+                            painted.err()
+                        );
+                        assert!(image.width() >= 40 && image.height() >= 1000);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::{fmt::Display, str::FromStr};
+
+use ab_glyph::PxScale;
+use image::Rgb;
+use serde::{Deserialize, Serialize};
+
+use crate::film::{
+    paint::{
+        constant::{BLACK, GRAY, WHITE},
+        text::{TextLayout, Typography, VerticalLayout},
+    },
+    FontChain, FontSet,
+};
+
+/// Size of lines if not set, relative to the standard padding.
+pub const DEFAULT_LINE_SIZE: f32 = 0.5;
+/// Sizes allowed for lines, relative to the standard padding.
+pub const LINE_SIZE_RANGE: std::ops::RangeInclusive<f32> = 0.1..=2.0;
+/// Scale of the capitals standing for lowercase letters in small caps.
+pub const SMALL_CAPS_SCALE: f32 = 0.75;
+
+/// Which font of the painter a line is painted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFont {
+    Main,
+    /// the sub font, or the main font if no sub font is given
+    Sub,
+    /// the caption font, or the main font if no caption font is given
+    Caption,
+}
+
+/// Letter case a line is transformed into before painting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextCase {
+    /// keep the text as it is
+    #[default]
+    None,
+    Upper,
+    Lower,
+    /// lowercase letters drawn as smaller capitals
+    SmallCaps,
+}
+
+impl TextCase {
+    fn name(&self) -> &'static str {
+        match self {
+            TextCase::None => "none",
+            TextCase::Upper => "upper",
+            TextCase::Lower => "lower",
+            TextCase::SmallCaps => "small-caps",
+        }
+    }
+}
+
+/// Style of a painted line, as set by the user.
+///
+/// It's written like `line=1,font=main,size=0.6,color=#202020,case=upper,opacity=0.8`, where
+/// every key is optional and the painter's own style is kept for the keys not set:
+///
+/// - `line`: the line to style from 1, in the order listed by the painter, the position of the
+///   style in the list if not set
+/// - `font`: `main`, `sub` or `caption`
+/// - `size`: font size relative to the standard padding, 0.1 to 2, the painters use 0.5
+/// - `color`: `#rrggbb`, `#rgb`, `black`, `gray` or `white`
+/// - `case`: `none`, `upper`, `lower` or `small-caps`
+/// - `opacity`: 0 to 1, blending the color into the background
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LineStyle {
+    pub line: Option<usize>,
+    pub font: Option<LineFont>,
+    pub size: Option<f32>,
+    pub color: Option<Rgb<u8>>,
+    pub case: Option<TextCase>,
+    pub opacity: Option<f32>,
+}
+
+impl FromStr for LineStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = LineStyle::default();
+        for pair in s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let (key, value) = match pair.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("expect `key=value` in line style, got `{}`", pair)),
+            };
+            match key {
+                "line" => {
+                    style.line = match value.parse::<usize>() {
+                        Ok(l) if l >= 1 => Some(l),
+                        _ => return Err(format!("line must be 1 or more, got `{}`", value)),
+                    }
+                }
+                "font" => {
+                    style.font = match value.to_ascii_lowercase().as_str() {
+                        "main" => Some(LineFont::Main),
+                        "sub" => Some(LineFont::Sub),
+                        "caption" => Some(LineFont::Caption),
+                        _ => {
+                            return Err(format!(
+                                "unknown line font `{}`, use main, sub or caption",
+                                value
+                            ))
+                        }
+                    }
+                }
+                "size" => {
+                    style.size = match value.parse::<f32>() {
+                        Ok(s) if LINE_SIZE_RANGE.contains(&s) => Some(s),
+                        _ => {
+                            return Err(format!(
+                                "line size must be {} to {}, got `{}`",
+                                LINE_SIZE_RANGE.start(),
+                                LINE_SIZE_RANGE.end(),
+                                value
+                            ))
+                        }
+                    }
+                }
+                "color" => style.color = Some(parse_color(value)?),
+                "case" => {
+                    style.case = match value.to_ascii_lowercase().as_str() {
+                        "none" => Some(TextCase::None),
+                        "upper" => Some(TextCase::Upper),
+                        "lower" => Some(TextCase::Lower),
+                        "small-caps" | "small_caps" => Some(TextCase::SmallCaps),
+                        _ => {
+                            return Err(format!(
+                                "unknown line case `{}`, use none, upper, lower or small-caps",
+                                value
+                            ))
+                        }
+                    }
+                }
+                "opacity" => {
+                    style.opacity = match value.parse::<f32>() {
+                        Ok(o) if (0.0..=1.0).contains(&o) => Some(o),
+                        _ => return Err(format!("line opacity must be 0 to 1, got `{}`", value)),
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown line style key `{}`, use line, font, size, color, case or opacity",
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(style)
+    }
+}
+
+/// Parse a color like `#rrggbb`, `#rgb` or a name.
+fn parse_color(value: &str) -> Result<Rgb<u8>, String> {
+    let invalid = || {
+        format!(
+            "line color must be `#rrggbb`, `#rgb`, black, gray or white, got `{}`",
+            value
+        )
+    };
+    match value.to_ascii_lowercase().as_str() {
+        "black" => return Ok(BLACK),
+        "gray" | "grey" => return Ok(GRAY),
+        "white" => return Ok(WHITE),
+        _ => {}
+    }
+    let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+    if !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let channels: Vec<u8> = match hex.len() {
+        3 => hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8 * 17))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?,
+        6 => (0..3)
+            .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?,
+        _ => return Err(invalid()),
+    };
+    Ok(Rgb([channels[0], channels[1], channels[2]]))
+}
+
+impl Display for LineStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut pairs = Vec::new();
+        if let Some(line) = self.line {
+            pairs.push(format!("line={}", line));
+        }
+        if let Some(font) = self.font {
+            pairs.push(format!(
+                "font={}",
+                match font {
+                    LineFont::Main => "main",
+                    LineFont::Sub => "sub",
+                    LineFont::Caption => "caption",
+                }
+            ));
+        }
+        if let Some(size) = self.size {
+            pairs.push(format!("size={}", size));
+        }
+        if let Some(Rgb([r, g, b])) = self.color {
+            pairs.push(format!("color=#{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if let Some(case) = self.case {
+            pairs.push(format!("case={}", case.name()));
+        }
+        if let Some(opacity) = self.opacity {
+            pairs.push(format!("opacity={}", opacity));
+        }
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl TryFrom<String> for LineStyle {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<LineStyle> for String {
+    fn from(value: LineStyle) -> Self {
+        value.to_string()
+    }
+}
+
+/// Style a line is painted in, with the user's style applied onto the painter's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub font: LineFont,
+    pub size: f32,
+    pub color: Rgb<u8>,
+    pub case: TextCase,
+    pub opacity: f32,
+}
+
+impl TextStyle {
+    /// Style of the emphasized lines, like the camera model: main font in black.
+    pub fn emphasized() -> Self {
+        TextStyle {
+            font: LineFont::Main,
+            size: DEFAULT_LINE_SIZE,
+            color: BLACK,
+            case: TextCase::None,
+            opacity: 1.0,
+        }
+    }
+
+    /// Style of the other lines: sub font in gray.
+    pub fn secondary() -> Self {
+        TextStyle {
+            font: LineFont::Sub,
+            color: GRAY,
+            ..TextStyle::emphasized()
+        }
+    }
+
+    fn apply(&mut self, style: &LineStyle) {
+        if let Some(font) = style.font {
+            self.font = font;
+        }
+        if let Some(size) = style.size {
+            self.size = size;
+        }
+        if let Some(color) = style.color {
+            self.color = color;
+        }
+        if let Some(case) = style.case {
+            self.case = case;
+        }
+        if let Some(opacity) = style.opacity {
+            self.opacity = opacity;
+        }
+    }
+
+    /// Style of the caption lines: caption font in black.
+    pub fn caption() -> Self {
+        TextStyle {
+            font: LineFont::Caption,
+            ..TextStyle::emphasized()
+        }
+    }
+
+    /// Pick the font of the line from the painter's fonts.
+    pub fn font<'a>(&self, fonts: &'a FontSet) -> &'a FontChain {
+        match self.font {
+            LineFont::Main => &fonts.main,
+            LineFont::Sub => fonts.sub.as_ref().unwrap_or(&fonts.main),
+            LineFont::Caption => fonts.caption.as_ref().unwrap_or(&fonts.main),
+        }
+    }
+
+    /// Scale of the font for the given standard padding.
+    pub fn scale(&self, standard_padding: u32) -> PxScale {
+        PxScale::from(standard_padding as f32 * self.size)
+    }
+
+    /// Color to paint with on the background, with the opacity blended in.
+    pub fn color_on(&self, background: Rgb<u8>) -> Rgb<u8> {
+        let mut ret = self.color;
+        for (channel, back) in ret.0.iter_mut().zip(background.0.iter()) {
+            *channel = (*channel as f32 * self.opacity + *back as f32 * (1.0 - self.opacity))
+                .round() as u8;
+        }
+        ret
+    }
+
+    /// Lay out the text of the line in the case of the style.
+    pub fn layout(
+        &self,
+        text: &str,
+        font: &FontChain,
+        scale: PxScale,
+        typography: &Typography,
+    ) -> TextLayout {
+        match self.case {
+            TextCase::None => TextLayout::new(text, font, scale, typography),
+            TextCase::Upper => TextLayout::new(&text.to_uppercase(), font, scale, typography),
+            TextCase::Lower => TextLayout::new(&text.to_lowercase(), font, scale, typography),
+            TextCase::SmallCaps => TextLayout::new_small_caps(text, font, scale, typography),
+        }
+    }
+
+    /// Lay out the text of the line as vertical text in the case of the style.
+    pub fn vertical_layout(
+        &self,
+        text: &str,
+        font: &FontChain,
+        scale: PxScale,
+        line_height: u32,
+        typography: &Typography,
+    ) -> VerticalLayout {
+        let text = match self.case {
+            TextCase::None => text.to_string(),
+            TextCase::Lower => text.to_lowercase(),
+            // vertical text has no small caps, capitals are closest
+            TextCase::Upper | TextCase::SmallCaps => text.to_uppercase(),
+        };
+        VerticalLayout::new(&text, font, scale, line_height, typography)
+    }
+}
+
+impl Typography {
+    /// Style of the line at index, from 0 in the order listed by the painter, with the styles
+    /// set by the user applied onto the painter's default.
+    pub fn line_style(&self, index: usize, default: TextStyle) -> TextStyle {
+        let mut ret = default;
+        for (position, style) in self.lines.iter().enumerate() {
+            let line = style.line.map_or(position, |l| l - 1);
+            if line == index {
+                ret.apply(style);
+            }
+        }
+        ret
+    }
+}
@@ -0,0 +1,697 @@
+use std::{ops::Range, str::FromStr};
+
+use ab_glyph::{point, Font, GlyphId, OutlinedGlyph, PxScale, Rect, ScaleFont};
+use image::{Rgb, RgbImage};
+use log::warn;
+use rustybuzz::{ttf_parser::Tag, Direction, Feature, UnicodeBuffer, Variation};
+use unicode_bidi::BidiInfo;
+use unicode_script::{Script, UnicodeScript};
+
+use crate::{
+    film::{
+        paint::{
+            constant::{ORIENTATION_HORIZONTAL, ORIENTATION_ROTATED, ORIENTATION_VERTICAL},
+            fit::Fit,
+            style::{LineStyle, SMALL_CAPS_SCALE},
+        },
+        FontChain,
+    },
+    utility::font::Typeface,
+};
+
+/// Tracking allowed, in 1/1000 em.
+pub const TRACKING_RANGE: std::ops::RangeInclusive<i32> = -500..=1000;
+
+/// How text is laid out, shared by all the lines a painter paints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typography {
+    /// extra space between glyphs in 1/1000 em, negative to tighten
+    pub tracking: i32,
+    /// apply the kerning of the font
+    pub kerning: bool,
+    /// apply the standard ligatures of the font, like `fi`
+    pub ligatures: bool,
+    /// orientation of the text in side borders, used by `duel` & `diagonal`
+    pub side_text: Orientation,
+    /// styles of the lines set by the user, the painter's own styles are used for the rest
+    pub lines: Vec<LineStyle>,
+    /// how lines too long for the painter are fitted in
+    pub fit: Fit,
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Typography {
+            tracking: 0,
+            kerning: true,
+            ligatures: true,
+            side_text: Orientation::Horizontal,
+            lines: Vec::new(),
+            fit: Fit::Shrink,
+        }
+    }
+}
+
+/// How a block of text is oriented.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// lines run left to right, stacked top to bottom
+    #[default]
+    Horizontal,
+    /// the horizontal block turned 90° to run along the long edge
+    Rotated,
+    /// lines run top to bottom, stacked right to left, like vertical CJK text
+    Vertical,
+}
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            ORIENTATION_HORIZONTAL => Ok(Orientation::Horizontal),
+            ORIENTATION_ROTATED => Ok(Orientation::Rotated),
+            ORIENTATION_VERTICAL => Ok(Orientation::Vertical),
+            _ => Err(format!(
+                "unknown orientation `{}`, use {}, {} or {}",
+                s, ORIENTATION_HORIZONTAL, ORIENTATION_ROTATED, ORIENTATION_VERTICAL
+            )),
+        }
+    }
+}
+
+/// A piece of text shaped at once, in a single face, script & direction.
+struct Item<'a> {
+    face: &'a Typeface,
+    script: Option<Script>,
+    range: Range<usize>,
+    /// lowercase letters to draw as smaller capitals
+    small: bool,
+}
+
+/// A glyph of shaped text, in font units.
+struct ShapedGlyph {
+    id: GlyphId,
+    x_advance: f32,
+    x_offset: f32,
+    y_offset: f32,
+}
+
+/// A line of text shaped & positioned, ready to be measured & drawn.
+///
+/// Glyphs are positioned from (0, 0) at the top-left of the line, with the baseline at the
+/// ascent of the main font. Text is reordered by the Unicode bidi algorithm, so right-to-left
+/// scripts like Arabic read correctly, and each script is shaped on its own so letters join.
+pub struct TextLayout {
+    text: String,
+    glyphs: Vec<OutlinedGlyph>,
+    advance: f32,
+    ink: Option<Rect>,
+    missing: Vec<char>,
+}
+
+impl TextLayout {
+    pub fn new(text: &str, font: &FontChain, scale: PxScale, typography: &Typography) -> Self {
+        Self::build(text, font, scale, typography, false)
+    }
+
+    /// Lay out text with its lowercase letters drawn as smaller capitals.
+    pub fn new_small_caps(
+        text: &str,
+        font: &FontChain,
+        scale: PxScale,
+        typography: &Typography,
+    ) -> Self {
+        Self::build(text, font, scale, typography, true)
+    }
+
+    fn build(
+        text: &str,
+        font: &FontChain,
+        scale: PxScale,
+        typography: &Typography,
+        small_caps: bool,
+    ) -> Self {
+        let baseline = font.primary().as_scaled(scale).ascent();
+        let tracking = scale.x * typography.tracking as f32 / 1000.0;
+
+        let mut glyphs = Vec::new();
+        let mut ink: Option<Rect> = None;
+        let mut x = 0.0;
+        let mut tracked = false;
+        let bidi = BidiInfo::new(text, None);
+        for paragraph in bidi.paragraphs.iter() {
+            // runs of the same direction, in visual order
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for run in runs {
+                let direction = match levels[run.start].is_rtl() {
+                    true => Direction::RightToLeft,
+                    false => Direction::LeftToRight,
+                };
+                // items of a right-to-left run are painted from the last one
+                let mut items = itemize(text, run, font, small_caps);
+                if direction == Direction::RightToLeft {
+                    items.reverse();
+                }
+
+                for item in items {
+                    let face = item.face;
+                    let (piece, item_scale) = match item.small {
+                        true => (
+                            text[item.range].to_uppercase(),
+                            PxScale {
+                                x: scale.x * SMALL_CAPS_SCALE,
+                                y: scale.y * SMALL_CAPS_SCALE,
+                            },
+                        ),
+                        false => (text[item.range].to_string(), scale),
+                    };
+                    let scaled_font = face.font.as_scaled(item_scale);
+                    let h_factor = scaled_font.h_scale_factor();
+                    let v_factor = scaled_font.v_scale_factor();
+                    let shaped = shape(face, &piece, item.script, direction, typography);
+                    for shaped in shaped {
+                        let glyph = shaped.id.with_scale_and_position(
+                            item_scale,
+                            point(
+                                x + shaped.x_offset * h_factor,
+                                baseline - shaped.y_offset * v_factor,
+                            ),
+                        );
+                        if let Some(outlined) = face.font.outline_glyph(glyph) {
+                            let bounds = outlined.px_bounds();
+                            ink = Some(match ink {
+                                Some(r) => Rect {
+                                    min: point(
+                                        r.min.x.min(bounds.min.x),
+                                        r.min.y.min(bounds.min.y),
+                                    ),
+                                    max: point(
+                                        r.max.x.max(bounds.max.x),
+                                        r.max.y.max(bounds.max.y),
+                                    ),
+                                },
+                                None => bounds,
+                            });
+                            glyphs.push(outlined);
+                        }
+                        x += shaped.x_advance * h_factor;
+                        // marks do not advance, so they stay with their base glyph
+                        if shaped.x_advance != 0.0 {
+                            x += tracking;
+                            tracked = true;
+                        }
+                    }
+                }
+            }
+        }
+        // no tracking after the last glyph
+        if tracked {
+            x -= tracking;
+        }
+
+        TextLayout {
+            text: text.to_string(),
+            glyphs,
+            advance: x.max(0.0),
+            ink,
+            missing: font.missing_chars(text),
+        }
+    }
+
+    /// Distance from the start of the line to where the next text would start.
+    pub fn advance(&self) -> f32 {
+        self.advance
+    }
+
+    /// Bounds of the pixels painted, relative to the top-left of the line.
+    pub fn ink_bounds(&self) -> Option<Rect> {
+        self.ink
+    }
+
+    /// Right edge of the line, where the ink ends rather than where the advance ends.
+    pub fn right_edge(&self) -> u32 {
+        match self.ink {
+            Some(r) => r.max.x.ceil().max(0.0) as u32,
+            None => self.advance.ceil() as u32,
+        }
+    }
+
+    /// Width the line takes, covering both its advance & its ink.
+    pub fn width(&self) -> u32 {
+        std::cmp::max(self.advance.ceil() as u32, self.right_edge())
+    }
+
+    /// Draw the line with its top-left at (x, y).
+    pub fn draw(&self, image: &mut RgbImage, x: i32, y: i32, color: &Rgb<u8>) {
+        warn_missing(&self.missing, &self.text);
+        self.paint(image, x, y, color);
+    }
+
+    /// Draw without warning about missing chars, for pieces of a line.
+    fn paint(&self, image: &mut RgbImage, x: i32, y: i32, color: &Rgb<u8>) {
+        self.each_pixel(|px, py, coverage| blend(image, x + px, y + py, color, coverage));
+    }
+
+    /// Draw the line turned 90° clockwise, so it runs top to bottom, with its top-left at
+    /// (x, y). The turned line is `height` wide, which is the height of the line before turning.
+    fn paint_sideways(&self, image: &mut RgbImage, x: i32, y: i32, height: u32, color: &Rgb<u8>) {
+        self.each_pixel(|px, py, coverage| {
+            blend(image, x + height as i32 - 1 - py, y + px, color, coverage)
+        });
+    }
+
+    /// Call f with the coverage of each pixel of the glyphs, relative to the top-left of the line.
+    fn each_pixel(&self, mut f: impl FnMut(i32, i32, f32)) {
+        for glyph in self.glyphs.iter() {
+            let bounds = glyph.px_bounds();
+            glyph.draw(|gx, gy, coverage| {
+                f(
+                    bounds.min.x.round() as i32 + gx as i32,
+                    bounds.min.y.round() as i32 + gy as i32,
+                    coverage.clamp(0.0, 1.0),
+                )
+            });
+        }
+    }
+}
+
+/// A line of text laid out top to bottom, as a column of vertical text.
+///
+/// CJK chars stand upright one by one, while runs of other chars, like Latin words & digits,
+/// are turned 90° clockwise, as vertical text is usually typeset.
+pub struct VerticalLayout {
+    text: String,
+    missing: Vec<char>,
+    /// pieces of the line from top to bottom, with whether they stand upright
+    segments: Vec<(TextLayout, bool)>,
+    width: u32,
+    em: u32,
+}
+
+impl VerticalLayout {
+    /// Lay out text in a column as wide as the line height.
+    pub fn new(
+        text: &str,
+        font: &FontChain,
+        scale: PxScale,
+        line_height: u32,
+        typography: &Typography,
+    ) -> Self {
+        let mut segments = Vec::new();
+        let mut sideways = String::new();
+        for c in text.chars() {
+            if !is_upright(c) {
+                sideways.push(c);
+                continue;
+            }
+            if !sideways.is_empty() {
+                let layout = TextLayout::new(&sideways, font, scale, typography);
+                segments.push((layout, false));
+                sideways.clear();
+            }
+            let layout = TextLayout::new(&c.to_string(), font, scale, typography);
+            segments.push((layout, true));
+        }
+        if !sideways.is_empty() {
+            segments.push((TextLayout::new(&sideways, font, scale, typography), false));
+        }
+
+        VerticalLayout {
+            text: text.to_string(),
+            missing: font.missing_chars(text),
+            segments,
+            width: line_height,
+            em: scale.y.ceil() as u32,
+        }
+    }
+
+    /// Width of the column.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the column, upright chars take an em each.
+    pub fn height(&self) -> u32 {
+        self.segments
+            .iter()
+            .map(|(layout, upright)| self.segment_height(layout, *upright))
+            .sum()
+    }
+
+    fn segment_height(&self, layout: &TextLayout, upright: bool) -> u32 {
+        match upright {
+            true => std::cmp::max(self.em, layout.width()),
+            false => layout.width(),
+        }
+    }
+
+    /// Draw the column with its top-left at (x, y), returning the bounds of the pixels painted
+    /// as (x, y, width, height).
+    pub fn draw(
+        &self,
+        image: &mut RgbImage,
+        x: i32,
+        y: i32,
+        color: &Rgb<u8>,
+    ) -> Option<(i32, i32, u32, u32)> {
+        warn_missing(&self.missing, &self.text);
+
+        let mut ink: Option<Rect> = None;
+        let mut curr_y = y;
+        for (layout, upright) in self.segments.iter() {
+            let height = self.segment_height(layout, *upright);
+            // ink of the segment in the image
+            let bounds = match upright {
+                true => {
+                    let seg_x = x + (self.width as i32 - layout.width() as i32) / 2;
+                    let seg_y = curr_y + (height as i32 - self.em as i32) / 2;
+                    layout.paint(image, seg_x, seg_y, color);
+                    layout.ink_bounds().map(|r| Rect {
+                        min: point(seg_x as f32 + r.min.x, seg_y as f32 + r.min.y),
+                        max: point(seg_x as f32 + r.max.x, seg_y as f32 + r.max.y),
+                    })
+                }
+                false => {
+                    layout.paint_sideways(image, x, curr_y, self.width, color);
+                    let right = (x + self.width as i32) as f32;
+                    layout.ink_bounds().map(|r| Rect {
+                        min: point(right - r.max.y, curr_y as f32 + r.min.x),
+                        max: point(right - r.min.y, curr_y as f32 + r.max.x),
+                    })
+                }
+            };
+            if let Some(b) = bounds {
+                ink = Some(match ink {
+                    Some(r) => Rect {
+                        min: point(r.min.x.min(b.min.x), r.min.y.min(b.min.y)),
+                        max: point(r.max.x.max(b.max.x), r.max.y.max(b.max.y)),
+                    },
+                    None => b,
+                });
+            }
+            curr_y += height as i32;
+        }
+        ink.map(|r| {
+            (
+                r.min.x.floor() as i32,
+                r.min.y.floor() as i32,
+                r.width().ceil() as u32,
+                r.height().ceil() as u32,
+            )
+        })
+    }
+}
+
+/// Whether c stands upright in vertical text, i.e. a CJK char or a full-width form.
+fn is_upright(c: char) -> bool {
+    match c.script() {
+        Script::Han | Script::Hiragana | Script::Katakana | Script::Hangul | Script::Bopomofo => {
+            true
+        }
+        // CJK symbols & punctuation, full-width forms
+        _ => matches!(c, '\u{3000}'..='\u{303f}' | '\u{ff01}'..='\u{ff60}'),
+    }
+}
+
+fn warn_missing(missing: &[char], text: &str) {
+    if !missing.is_empty() {
+        warn!(
+            "no font can render characters {:?} in text: {}",
+            missing.iter().collect::<String>(),
+            text
+        );
+    }
+}
+
+/// Blend color onto the pixel at (x, y) by coverage, skipping pixels out of the image.
+fn blend(image: &mut RgbImage, x: i32, y: i32, color: &Rgb<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i32 || y >= image.height() as i32 {
+        return;
+    }
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for (channel, target) in pixel.0.iter_mut().zip(color.0.iter()) {
+        *channel = (*channel as f32 * (1.0 - coverage) + *target as f32 * coverage).round() as u8;
+    }
+}
+
+/// Split a run of text into items by the script & the face of the chars, in logical order,
+/// and by lowercase letters for small caps.
+///
+/// Common chars like spaces & digits, and marks, stay in the item before them if its face has
+/// them, so a word is not broken across faces.
+fn itemize<'a>(
+    text: &str,
+    range: Range<usize>,
+    font: &'a FontChain,
+    small_caps: bool,
+) -> Vec<Item<'a>> {
+    let mut items: Vec<Item> = Vec::new();
+    for (offset, c) in text[range.clone()].char_indices() {
+        let index = range.start + offset;
+        let script = match c.script() {
+            Script::Common | Script::Inherited | Script::Unknown => None,
+            s => Some(s),
+        };
+        let small = small_caps && c.is_lowercase();
+
+        if let Some(item) = items.last_mut() {
+            let face = match script {
+                None if item.face.font.glyph_id(c).0 != 0 => item.face,
+                _ => font.face_for(c).unwrap_or(font.primary_face()),
+            };
+            let same_script = script.is_none() || item.script.is_none() || item.script == script;
+            if same_script && std::ptr::eq(face, item.face) && small == item.small {
+                item.script = item.script.or(script);
+                item.range.end = index + c.len_utf8();
+                continue;
+            }
+            items.push(Item {
+                face,
+                script,
+                range: index..index + c.len_utf8(),
+                small,
+            });
+            continue;
+        }
+
+        items.push(Item {
+            face: font.face_for(c).unwrap_or(font.primary_face()),
+            script,
+            range: index..index + c.len_utf8(),
+            small,
+        });
+    }
+    items
+}
+
+/// Shape a piece of text with a single face, applying its kerning & ligatures as set.
+fn shape(
+    face: &Typeface,
+    text: &str,
+    script: Option<Script>,
+    direction: Direction,
+    typography: &Typography,
+) -> Vec<ShapedGlyph> {
+    let mut hb_face = match rustybuzz::Face::from_slice(face.font.as_slice(), face.index) {
+        Some(f) => f,
+        None => return shape_simple(face, text, direction, typography),
+    };
+    let variations: Vec<Variation> = face
+        .variation
+        .iter()
+        .map(|(tag, value)| Variation {
+            tag: Tag::from_bytes(tag),
+            value: *value,
+        })
+        .collect();
+    hb_face.set_variations(&variations);
+
+    let mut features = Vec::new();
+    if !typography.kerning {
+        features.push(Feature::new(Tag::from_bytes(b"kern"), 0, ..));
+    }
+    if !typography.ligatures {
+        features.push(Feature::new(Tag::from_bytes(b"liga"), 0, ..));
+        features.push(Feature::new(Tag::from_bytes(b"clig"), 0, ..));
+    }
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(direction);
+    if let Some(script) = script.and_then(|s| {
+        let tag: [u8; 4] = s.short_name().as_bytes().try_into().ok()?;
+        rustybuzz::Script::from_iso15924_tag(Tag::from_bytes(&tag))
+    }) {
+        buffer.set_script(script);
+    }
+    let output = rustybuzz::shape(&hb_face, &features, buffer);
+    output
+        .glyph_infos()
+        .iter()
+        .zip(output.glyph_positions())
+        .map(|(info, position)| ShapedGlyph {
+            id: GlyphId(info.glyph_id as u16),
+            x_advance: position.x_advance as f32,
+            x_offset: position.x_offset as f32,
+            y_offset: position.y_offset as f32,
+        })
+        .collect()
+}
+
+/// Lay out chars one by one with the `kern` table only, for faces the shaper cannot read.
+fn shape_simple(
+    face: &Typeface,
+    text: &str,
+    direction: Direction,
+    typography: &Typography,
+) -> Vec<ShapedGlyph> {
+    // kern in visual order, where the pairs of the `kern` table are from left to right
+    let chars: Vec<char> = match direction {
+        Direction::RightToLeft => text.chars().rev().collect(),
+        _ => text.chars().collect(),
+    };
+    let mut ret: Vec<ShapedGlyph> = Vec::new();
+    for c in chars {
+        let id = face.font.glyph_id(c);
+        if typography.kerning {
+            if let Some(left) = ret.last_mut() {
+                left.x_advance += face.font.kern_unscaled(left.id, id);
+            }
+        }
+        ret.push(ShapedGlyph {
+            id,
+            x_advance: face.font.h_advance_unscaled(id),
+            x_offset: 0.0,
+            y_offset: 0.0,
+        });
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::film::{
+        font::fixture::{chain, SANS, SANS_LIGHT},
+        paint::{
+            fit::{fit_lines, StyledLine},
+            style::TextStyle,
+        },
+    };
+
+    const SCALE: PxScale = PxScale { x: 40.0, y: 40.0 };
+
+    /// Glyph ids of the chars in the face, skipping those without ink like spaces.
+    fn glyph_ids(face: &Typeface, text: &str) -> Vec<GlyphId> {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| face.font.glyph_id(c))
+            .collect()
+    }
+
+    #[test]
+    fn mixed_direction_line_is_in_visual_order() {
+        let font = chain(SANS, &[]);
+        let layout = TextLayout::new("ab אבג cd", &font, SCALE, &Typography::default());
+
+        // the hebrew word reads from right to left within the left-to-right line
+        let ids: Vec<GlyphId> = layout.glyphs.iter().map(|g| g.glyph().id).collect();
+        assert_eq!(ids, glyph_ids(font.primary_face(), "ab גבא cd"));
+        let xs: Vec<f32> = layout.glyphs.iter().map(|g| g.glyph().position.x).collect();
+        assert!(xs.windows(2).all(|w| w[0] < w[1]), "{:?}", xs);
+    }
+
+    #[test]
+    fn fitted_line_is_drawn_as_wide_as_it_is_shaped() {
+        let font = chain(SANS, &[]);
+        let typography = Typography {
+            tracking: 80,
+            kerning: false,
+            ligatures: false,
+            ..Typography::default()
+        };
+        let text = "Rustant 24mm";
+        let line = StyledLine {
+            text,
+            font: &font,
+            style: TextStyle::emphasized(),
+        };
+        let scale = line.style.scale(40);
+        let fitted = fit_lines(&[line], 40, Some(10_000), None, &typography, |_, s| {
+            s.y as u32
+        });
+        assert_eq!(fitted.len(), 1);
+        let layout = &fitted[0].layout;
+        assert_eq!(fitted[0].scale, scale);
+
+        // plain advances plus the tracking between glyphs, as kerning & ligatures are off
+        let scaled = font.primary().as_scaled(scale);
+        let advances: f32 = text
+            .chars()
+            .map(|c| scaled.h_advance(scaled.glyph_id(c)))
+            .sum();
+        let tracking = scale.x * typography.tracking as f32 / 1000.0;
+        let shaped = advances + tracking * (text.chars().count() - 1) as f32;
+        assert!(
+            (layout.advance() - shaped).abs() < 0.01,
+            "{} != {}",
+            layout.advance(),
+            shaped
+        );
+
+        // the ink drawn stays within the width fit_lines measured
+        let mut image = RgbImage::from_pixel(
+            layout.width() + 20,
+            scale.y as u32 * 2,
+            Rgb([255, 255, 255]),
+        );
+        layout.draw(&mut image, 10, 0, &Rgb([0, 0, 0]));
+        let painted: Vec<u32> = image
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p.0 != [255, 255, 255])
+            .map(|(x, _, _)| x)
+            .collect();
+        let (left, right) = (
+            *painted.iter().min().unwrap(),
+            *painted.iter().max().unwrap() + 1,
+        );
+        assert!(
+            left >= 10 && right <= 10 + layout.width(),
+            "{}..{} of {}",
+            left,
+            right,
+            layout.width()
+        );
+        assert!(
+            right + 1 >= 10 + layout.right_edge(),
+            "{} != {}",
+            right,
+            layout.right_edge()
+        );
+    }
+
+    #[test]
+    fn fallback_font_is_used_for_the_runs_of_other_scripts() {
+        let font = chain(SANS_LIGHT, &[SANS]);
+        let text = "Canon كانون EOS";
+        let items = itemize(text, 0..text.len(), &font, false);
+
+        let runs: Vec<(&str, bool)> = items
+            .iter()
+            .map(|i| {
+                (
+                    &text[i.range.clone()],
+                    std::ptr::eq(i.face, font.primary_face()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            runs,
+            vec![("Canon ", true), ("كانون ", false), ("EOS", true)]
+        );
+        assert_eq!(items[1].script, Some(Script::Arabic));
+        let layout = TextLayout::new(text, &font, SCALE, &Typography::default());
+        assert!(layout.missing.is_empty());
+    }
+}
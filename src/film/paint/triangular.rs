@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use ab_glyph::{Font, PxScale, ScaleFont};
 use image::{
     imageops::{resize, FilterType},
     GenericImage, Rgb, RgbImage,
@@ -8,61 +8,80 @@ use image::{
 use log::{debug, trace};
 
 use crate::{
-    entity::{ExifInfo, Padding, Position},
+    entity::{Caption, ExifInfo, Padding, Position},
     film::{
         paint::{
-            add_padding, add_text, add_vertical_line,
-            constant::{BLACK, GOLDEN_RATIO, GRAY, WHITE},
-            create_canvas, get_text_scaled_length, Painter,
+            add_padding, add_vertical_line,
+            caption::caption_lines,
+            constant::{GRAY, TRIANGLULAR_PAINTER, WHITE},
+            create_canvas, create_lines_canvas, fit_logo,
+            layout::{Layout, RegionKind},
+            fit::{fit_lines, FittedLine, StyledLine},
+            frame::{standard_padding, Frame},
+            style::TextStyle,
+            text::Typography,
+            Painter,
         },
-        LogoCache,
+        FontSet, LogoCache,
     },
 };
 
+/// Number of lines painted on the left, the rest are on the right.
+const LEFT_LINES: usize = 2;
+
 pub struct TriangularPainter {
     cache: Arc<LogoCache>,
-    font: Arc<FontVec>,
-    sub_font: Arc<Option<FontVec>>,
+    fonts: FontSet,
     main_position: Position,
     pad_around: bool,
+    typography: Typography,
+    frame: Frame,
 }
 
 impl TriangularPainter {
     pub fn new(
         cache: Arc<LogoCache>,
-        font: Arc<FontVec>,
-        sub_font: Arc<Option<FontVec>>,
+        fonts: FontSet,
         main_position: Position,
         pad_around: bool,
+        typography: Typography,
+        frame: Frame,
     ) -> Self {
         TriangularPainter {
             cache,
-            font,
-            sub_font,
+            fonts,
             main_position,
             pad_around,
+            typography,
+            frame,
         }
     }
 
-    pub fn new_normal(cache: Arc<LogoCache>, font: Arc<FontVec>) -> Self {
+    pub fn new_normal(
+        cache: Arc<LogoCache>,
+        fonts: FontSet,
+        typography: Typography,
+        frame: Frame,
+    ) -> Self {
         TriangularPainter {
             cache,
-            font,
-            sub_font: None.into(),
+            fonts: FontSet { sub: None, ..fonts },
             main_position: Position::BOTTOM,
             pad_around: true,
+            typography,
+            frame,
         }
     }
 
-    fn get_lens_model_text(&self, exif_info: &ExifInfo) -> String {
+    fn get_lens_model_text(exif_info: &ExifInfo) -> String {
         exif_info.lens_model.clone().unwrap_or("".to_string())
     }
 
-    fn get_camera_model_text(&self, exif_info: &ExifInfo) -> String {
+    fn get_camera_model_text(exif_info: &ExifInfo) -> String {
         exif_info.camera_model.clone().unwrap_or("".to_string())
     }
 
-    fn get_shooting_parameters_text(&self, exif_info: &ExifInfo) -> String {
+    fn get_shooting_parameters_text(exif_info: &ExifInfo) -> String {
         let focal_length: String = exif_info
             .focal_length
             .clone()
@@ -100,10 +119,23 @@ impl TriangularPainter {
         details.join(" ")
     }
 
-    fn get_datetime_text(&self, exif_info: &ExifInfo) -> String {
+    fn get_datetime_text(exif_info: &ExifInfo) -> String {
         exif_info.datetime.clone().unwrap_or("".to_string())
     }
 
+    /// Text lines to paint, the `LEFT_LINES` left ones first, empty lines are skipped when
+    /// painting.
+    ///
+    /// The first line on each side is emphasized.
+    pub fn get_lines(exif_info: &ExifInfo) -> Vec<String> {
+        vec![
+            Self::get_lens_model_text(exif_info),
+            Self::get_camera_model_text(exif_info),
+            Self::get_shooting_parameters_text(exif_info),
+            Self::get_datetime_text(exif_info),
+        ]
+    }
+
     ///
     /// The layout of main content canvas is like:
     ///
@@ -124,210 +156,303 @@ impl TriangularPainter {
     /// In side of the `main content`, it shows something like:
     ///
     /// ```txt
-    /// +---------------------------------------------+
-    /// |                                             |
-    /// |                                             |
-    /// | LensModel         Logo | ShootingParameters |
-    /// | CameraModel       Logo | Datetime           |
-    /// |                                             |
-    /// |                                             |
-    /// +---------------------------------------------+
+    /// +-----------------------------------------------------------+
+    /// |                                                           |
+    /// |                                                           |
+    /// | LensModel     Title       Logo | ShootingParameters       |
+    /// | CameraModel   Location    Logo | Datetime                 |
+    /// |               Signature                                   |
+    /// |                                                           |
+    /// +-----------------------------------------------------------+
     /// ```
+    ///
+    /// The caption is centered, or as close to the center as the lines on the sides allow.
     fn create_main_content_canvas(
         &self,
-        width: u32,
-        height: u32,
+        (width, height): (u32, u32),
         exif_info: &ExifInfo,
-        font_scale: &PxScale,
+        caption: &Caption,
+        standard_padding: u32,
         padding: &Padding,
         background: Rgb<u8>,
-    ) -> Result<RgbImage, Box<dyn std::error::Error>> {
+    ) -> Result<(RgbImage, Layout), Box<dyn std::error::Error>> {
         debug!(
             "creating main content with width {}, height {}, padding: {:?}",
             width, height, padding
         );
+        let font_scale = TextStyle::emphasized().scale(standard_padding);
+
+        let mut layout = Layout::new();
 
         // create canvas for main content
         let mut canvas = create_canvas(width, height, background);
 
+        // get the logo
+        let logo_name = exif_info.camera_maker.as_deref().unwrap_or("");
+        let logo = self.cache.get(logo_name).map(|logo| {
+            let (logo_ori_width, logo_ori_height) = logo.dimensions();
+            let mut logo_new_height = font_scale.y * 0.75;
+            if (logo_ori_width as f32 / logo_ori_height as f32) <= 1.5 {
+                // logo not too wide
+                logo_new_height = font_scale.y * 1.75;
+            }
+            let logo_new_width = logo_new_height * logo_ori_width as f32 / logo_ori_height as f32;
+            debug!(
+                "logo to paint has width: {}, height: {}",
+                logo_new_width as u32, logo_new_height as u32
+            );
+            resize(
+                logo,
+                logo_new_width as u32,
+                logo_new_height as u32,
+                FilterType::Lanczos3,
+            )
+        });
+
+        // the logo takes at most half of the width inside the paddings, the lines share the rest
+        let gaps = (font_scale.y * 3.0) as u32;
+        let max_logo_width = width.saturating_sub(padding.left + padding.right + gaps) / 2;
+        let logo = logo.and_then(|logo| fit_logo(logo, Some(max_logo_width), None));
+
+        // fit the lines on both sides & the caption in the middle, first within the height, then
+        // share the width between them if they overlap each other or the logo
+        let texts = Self::get_lines(exif_info);
+        let (left_texts, right_texts) = texts.split_at(LEFT_LINES);
+        let has_text_on_left = left_texts.iter().any(|t| !t.is_empty());
+        let has_text_on_right = right_texts.iter().any(|t| !t.is_empty());
+        let left_lines = match has_text_on_left {
+            true => self.styled_lines(left_texts, 0),
+            false => Vec::new(),
+        };
+        let right_lines = match has_text_on_right {
+            true => self.styled_lines(right_texts, LEFT_LINES),
+            false => Vec::new(),
+        };
+        let max_height = Some(height.saturating_sub(padding.top));
+        let mut left = self.fit_lines(&left_lines, standard_padding, None, max_height);
+        let mut right = self.fit_lines(&right_lines, standard_padding, None, max_height);
+        let middle_lines = caption_lines(caption, &self.fonts, &self.typography);
+        let mut middle = self.fit_lines(&middle_lines, standard_padding, None, max_height);
+
+        let logo_space = match (&logo, has_text_on_right) {
+            (Some(logo), true) => logo.width() + (font_scale.y * 2.0) as u32,
+            _ => 0,
+        };
+        // a space on both sides of the caption instead of one between the sides
+        let caption_space = match middle.is_empty() {
+            true => 0,
+            false => font_scale.y as u32,
+        };
+        let available_width = width.saturating_sub(
+            padding.left + padding.right + logo_space + font_scale.y as u32 + caption_space,
+        );
+        let (left_width, middle_width, right_width) = (
+            Self::block_width(&left),
+            Self::block_width(&middle),
+            Self::block_width(&right),
+        );
+        let total_width = left_width + middle_width + right_width;
+        if total_width > available_width {
+            let share = |block_width: u32| {
+                (available_width as u64 * block_width as u64 / total_width as u64) as u32
+            };
+            let (left_max, middle_max) = (share(left_width), share(middle_width));
+            let right_max = available_width - left_max - middle_max;
+            debug!(
+                "lines too wide for {}, fit the left in {}, the caption in {} & the right in {}",
+                available_width, left_max, middle_max, right_max
+            );
+            left = self.fit_lines(&left_lines, standard_padding, Some(left_max), max_height);
+            middle = self.fit_lines(&middle_lines, standard_padding, Some(middle_max), max_height);
+            right = self.fit_lines(&right_lines, standard_padding, Some(right_max), max_height);
+        }
+
         // print lines on the left
         trace!("paint main text to the left");
-        let lens_model_text = self.get_lens_model_text(exif_info);
-        let camera_model_text = self.get_camera_model_text(exif_info);
-        if !lens_model_text.is_empty() || !camera_model_text.is_empty() {
-            let text_canvas = self.create_text_canvas_with_emphasized_first_line(
-                &vec![lens_model_text, camera_model_text],
-                &self.font,
-                &self.sub_font,
-                font_scale,
-                Position::LEFT,
-                background,
-            );
+        let mut left_text_canvas_width: u32 = 0;
+        if has_text_on_left {
+            let text_canvas =
+                self.create_text_canvas_with_emphasized_first_line(&left, Position::LEFT, background);
+            left_text_canvas_width = text_canvas.width();
             canvas.copy_from(&text_canvas, padding.left, padding.top)?;
+            layout.add(
+                RegionKind::Text,
+                padding.left,
+                padding.top,
+                text_canvas.width(),
+                text_canvas.height(),
+            );
         }
 
         // print lines on the right
         trace!("paint main text to the right");
-        let shooting_parameters_text = self.get_shooting_parameters_text(exif_info);
-        let datetime_text = self.get_datetime_text(exif_info);
-        let has_text_on_right = !shooting_parameters_text.is_empty() || !datetime_text.is_empty();
         let mut right_text_canvas_width: u32 = 0;
         if has_text_on_right {
             let text_canvas = self.create_text_canvas_with_emphasized_first_line(
-                &vec![shooting_parameters_text, datetime_text],
-                &self.font,
-                &self.sub_font,
-                font_scale,
+                &right,
                 Position::RIGHT,
                 background,
             );
             let (text_canvas_width, _) = text_canvas.dimensions();
             right_text_canvas_width = text_canvas_width;
 
-            canvas.copy_from(
-                &text_canvas,
-                width - padding.right - right_text_canvas_width,
+            let x = width.saturating_sub(padding.right + right_text_canvas_width);
+            canvas.copy_from(&text_canvas, x, padding.top)?;
+            layout.add(
+                RegionKind::Text,
+                x,
                 padding.top,
-            )?;
+                text_canvas.width(),
+                text_canvas.height(),
+            );
         }
 
         // print vertical delimiter if possible
-        let logo_name = exif_info.camera_maker.as_deref().unwrap_or("");
-        let logo = self.cache.get(logo_name);
-        let has_logo_on_right = self.cache.get(logo_name).is_some();
+        let has_logo_on_right = logo.is_some();
         if has_text_on_right && has_logo_on_right {
             trace!("paint vertical delimiter to the right");
+            let delimiter_x =
+                width.saturating_sub(padding.right + right_text_canvas_width + font_scale.y as u32);
+            let delimiter_thickness = std::cmp::max((font_scale.y / 32.0) as u32, 1);
             add_vertical_line(
                 &mut canvas,
-                width - padding.right - right_text_canvas_width - font_scale.y as u32,
+                delimiter_x,
                 padding.top,
                 padding.top,
-                std::cmp::max((font_scale.y / 32.0) as u32, 1),
+                delimiter_thickness,
                 &GRAY,
-                &background,
+            );
+            layout.add(
+                RegionKind::Delimiter,
+                delimiter_x.saturating_sub(delimiter_thickness / 2),
+                padding.top,
+                delimiter_thickness / 2 * 2 + 1,
+                padding.top,
             );
         }
 
         // print logo
         trace!("paint logo to the right");
-        if has_logo_on_right {
-            let logo = logo.unwrap();
-            let (logo_ori_width, logo_ori_height) = logo.dimensions();
-            let mut logo_new_height = font_scale.y * 0.75;
-            if (logo_ori_width as f32 / logo_ori_height as f32) <= 1.5 {
-                // logo not too wide
-                logo_new_height = font_scale.y * 1.75;
-            }
-            let logo_new_width = logo_new_height * logo_ori_width as f32 / logo_ori_height as f32;
-            let logo: RgbImage = resize(
-                logo,
-                logo_new_width as u32,
-                logo_new_height as u32,
-                FilterType::Lanczos3,
-            );
-            debug!(
-                "logo to paint has width: {}, height: {}",
-                logo_new_width as u32, logo_new_height as u32
+        let mut right_start = width.saturating_sub(padding.right + right_text_canvas_width);
+        if let Some(logo) = logo {
+            let logo_x =
+                right_start.saturating_sub((font_scale.y * 2.0) as u32 + logo.width());
+            let logo_y = padding.top + padding.top.saturating_sub(logo.height()) / 2;
+            canvas.copy_from(&logo, logo_x, logo_y)?;
+            layout.add(RegionKind::Logo, logo_x, logo_y, logo.width(), logo.height());
+            right_start = logo_x;
+        }
+
+        // print the caption centered, or as close to the center as the sides allow
+        if !middle.is_empty() {
+            trace!("paint caption in the middle");
+            let text_canvas =
+                self.create_text_canvas_with_emphasized_first_line(&middle, Position::MIDDLE, background);
+            let min_x = padding.left + left_text_canvas_width + font_scale.y as u32;
+            let max_x = right_start
+                .saturating_sub(font_scale.y as u32 + text_canvas.width())
+                .max(min_x);
+            let caption_x = (width.saturating_sub(text_canvas.width()) / 2).clamp(min_x, max_x);
+            debug!("paint caption at x: {}, y: {}", caption_x, padding.top);
+            canvas.copy_from(&text_canvas, caption_x, padding.top)?;
+            layout.add(
+                RegionKind::Caption,
+                caption_x,
+                padding.top,
+                text_canvas.width(),
+                text_canvas.height(),
             );
-            canvas.copy_from(
-                &logo,
-                width
-                    - padding.right
-                    - right_text_canvas_width
-                    - (font_scale.y * 2.0) as u32
-                    - logo_new_width as u32,
-                padding.top + (padding.top - logo_new_height as u32) / 2,
-            )?;
         }
 
-        Ok(canvas)
+        Ok((canvas, layout))
     }
 
-    fn create_text_canvas_with_emphasized_first_line(
-        &self,
-        lines: &Vec<String>,
-        font: &FontVec,
-        sub_font: &Option<FontVec>,
-        scale: &PxScale,
-        align: Position,
-        background: Rgb<u8>,
-    ) -> RgbImage {
-        // quick return
-        if lines.is_empty() {
-            let mut ret = RgbImage::new(1, 1);
-            ret.put_pixel(0, 0, background);
-            return ret;
-        }
-
-        // calculate the size of canvas
-        let scaled_font = font.as_scaled(*scale);
-        let height = std::cmp::max(scaled_font.height() as u32 * lines.len() as u32, 1);
-        let width = lines
+    /// Lines of a side in their styles, the first of which is at `first_index` in the painter's
+    /// lines and emphasized.
+    fn styled_lines<'a>(&'a self, texts: &'a [String], first_index: usize) -> Vec<StyledLine<'a>> {
+        texts
             .iter()
-            .map(|line| get_text_scaled_length(line, font, scale))
-            .max()
-            .unwrap_or(1);
-
-        // create the canvas
-        let mut canvas = create_canvas(width, height, background);
-
-        // print lines on it
-        let mut index: u32 = 0;
-        for line in lines {
-            let color = match index {
-                0 => &BLACK,
-                _ => &GRAY,
-            };
-            let font_to_use = match index {
-                0 => font,
-                _ => sub_font.as_ref().unwrap_or(font),
-            };
+            .enumerate()
+            .map(|(index, text)| {
+                let default = match index {
+                    0 => TextStyle::emphasized(),
+                    _ => TextStyle::secondary(),
+                };
+                let style = self.typography.line_style(first_index + index, default);
+                StyledLine {
+                    text,
+                    font: style.font(&self.fonts),
+                    style,
+                }
+            })
+            .collect()
+    }
 
-            let mut x = 0;
-            let y = index * (scaled_font.height() as u32);
+    fn fit_lines<'a>(
+        &self,
+        lines: &[StyledLine<'a>],
+        standard_padding: u32,
+        max_width: Option<u32>,
+        max_height: Option<u32>,
+    ) -> Vec<FittedLine<'a>> {
+        fit_lines(
+            lines,
+            standard_padding,
+            max_width,
+            max_height,
+            &self.typography,
+            |_, scale| self.line_height(scale),
+        )
+    }
 
-            // adjust x position if align to right
-            if align == Position::RIGHT {
-                // get rendered length of current line
-                let line_width = get_text_scaled_length(line, font_to_use, scale);
-                x = width - line_width;
-            }
+    /// Lines are spaced by the height of the main font, whatever font they are in.
+    fn line_height(&self, scale: PxScale) -> u32 {
+        self.fonts.main.primary().as_scaled(scale).height() as u32
+    }
 
-            // paint the lines to canvas
-            add_text(&mut canvas, x, y, &line, scale, font_to_use, color);
-            index += 1;
-        }
+    fn block_width(lines: &[FittedLine]) -> u32 {
+        lines
+            .iter()
+            .map(|line| line.layout.width())
+            .max()
+            .unwrap_or(0)
+    }
 
-        return canvas;
+    fn create_text_canvas_with_emphasized_first_line(
+        &self,
+        lines: &[FittedLine],
+        align: Position,
+        background: Rgb<u8>,
+    ) -> RgbImage {
+        create_lines_canvas(lines, align, |line| self.line_height(line.scale), background)
     }
 }
 
 impl Painter for TriangularPainter {
-    fn paint(
+    fn name(&self) -> &str {
+        TRIANGLULAR_PAINTER
+    }
+
+    fn paint_layout(
         &self,
         image: &mut image::RgbImage,
         exif_info: &ExifInfo,
+        caption: &Caption,
+        scale: f32,
+        layout: &mut Layout,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let (ori_width, ori_height) = image.dimensions();
         let long_side = std::cmp::max(ori_width, ori_height);
         debug!("origin image width: {}, height: {}", ori_width, ori_height);
 
         // setup padding size
-        let standard_padding_ratio = 1.0 / GOLDEN_RATIO / 16.0;
-        let standard_padding = (long_side as f32 * standard_padding_ratio) as u32; // twice of the scaled font height
+        let standard_padding = standard_padding(long_side); // twice of the scaled font height
         let main_padding = standard_padding * 3; // 3 times for main
         let mut trivial_padding: u32 = 0;
         if self.pad_around {
             trivial_padding = standard_padding; // 1 times for other
         }
 
-        // prepare font
-        let font_size = standard_padding as f32 * 0.5;
-        let font_scale = PxScale {
-            x: font_size,
-            y: font_size,
-        };
-
         // create a new main content canvas
 
         // add padding around the origin image
@@ -341,7 +466,15 @@ impl Painter for TriangularPainter {
             padding.top = main_padding;
             padding.bottom = trivial_padding;
         }
+        // the frame never leaves less than the main content needs
+        self.frame.apply(&mut padding, ori_width, ori_height, scale);
+        let main_side = match self.main_position {
+            Position::TOP => &mut padding.top,
+            _ => &mut padding.bottom,
+        };
+        *main_side = (*main_side).max(main_padding);
         add_padding(image, &padding, &WHITE)?;
+        layout.add_padding(ori_width, ori_height, &padding);
 
         // create a new main content canvas
         let main_content_canvas_padding = Padding::new(
@@ -350,21 +483,22 @@ impl Painter for TriangularPainter {
             padding.left + standard_padding / 2,
             padding.right + standard_padding / 2,
         );
-        let main_content_canvas = self.create_main_content_canvas(
-            ori_width + trivial_padding * 2,
-            main_padding,
+        let (main_content_canvas, main_content_layout) = self.create_main_content_canvas(
+            (ori_width + padding.left + padding.right, main_padding),
             exif_info,
-            &font_scale,
+            caption,
+            standard_padding,
             &main_content_canvas_padding,
             WHITE,
         )?;
 
-        // put main content canvas back
-        if self.main_position == Position::TOP {
-            image.copy_from(&main_content_canvas, 0, 0)?;
-        } else {
-            image.copy_from(&main_content_canvas, 0, ori_height + padding.top)?;
-        }
+        // put main content canvas back, in the middle of the main side
+        let main_content_y = match self.main_position {
+            Position::TOP => (padding.top - main_padding) / 2,
+            _ => ori_height + padding.top + (padding.bottom - main_padding) / 2,
+        };
+        image.copy_from(&main_content_canvas, 0, main_content_y)?;
+        layout.add_at(main_content_layout, 0, main_content_y);
 
         Ok(())
     }
@@ -0,0 +1,101 @@
+use std::{error::Error, fs, io::Cursor};
+
+use exif::Reader;
+use log::{error, warn};
+
+use crate::{
+    argument::InspectArguments,
+    command::{EXIT_DEVELOP_FAILED, EXIT_OK},
+    entity::{ExifInfo, InspectReport, PainterLines, RawTag},
+    film::{
+        paint::{get_painter_lines, PAINTERS},
+        LogoCache,
+    },
+};
+
+/// Output format of `inspect`.
+pub const FORMAT_TEXT: &str = "text";
+pub const FORMAT_JSON: &str = "json";
+
+/// Read the EXIF of the file data, and tell what rustant-film would make of it.
+pub fn inspect(file: &str, data: &[u8], logos: &LogoCache) -> Result<InspectReport, Box<dyn Error>> {
+    let exif = Reader::new().read_from_container(&mut Cursor::new(data))?;
+    let exif_info = ExifInfo::new(&exif);
+
+    let tags = exif
+        .fields()
+        .map(|field| RawTag {
+            ifd: field.ifd_num.to_string(),
+            tag: field.tag.to_string(),
+            value: field.display_value().with_unit(&exif).to_string(),
+        })
+        .collect();
+    let logo = logos
+        .resolve(exif_info.camera_maker.as_deref().unwrap_or(""))
+        .map(|name| name.to_string());
+    let painters = PAINTERS
+        .iter()
+        .map(|(name, _)| PainterLines {
+            painter: name.to_string(),
+            lines: get_painter_lines(name, &exif_info),
+        })
+        .collect();
+
+    Ok(InspectReport {
+        file: file.to_string(),
+        exif: exif_info,
+        tags,
+        logo,
+        painters,
+    })
+}
+
+/// Print what rustant-film extracts from each of the files.
+pub fn run(args: InspectArguments) -> u8 {
+    let mut logo_cache = LogoCache::new();
+    if let Err(e) = logo_cache.load(args.logos()) {
+        warn!(
+            "cannot read logos from file {}, no logo is resolved, cause: {}",
+            args.logos(),
+            e
+        );
+    }
+
+    let mut code = EXIT_OK;
+    let mut reports = Vec::new();
+    for path in &args.files {
+        let data = match fs::read(path) {
+            Ok(d) => d,
+            Err(e) => {
+                error!("cannot open file at {}, cause: {}", path.display(), e);
+                code = EXIT_DEVELOP_FAILED;
+                continue;
+            }
+        };
+        match inspect(&path.display().to_string(), &data, &logo_cache) {
+            Ok(report) => reports.push(report),
+            Err(e) => {
+                error!("cannot read EXIF from file {}, cause: {}", path.display(), e);
+                code = EXIT_DEVELOP_FAILED;
+            }
+        }
+    }
+
+    if args.format == FORMAT_JSON {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                error!("cannot serialize inspect reports, cause: {}", e);
+                return EXIT_DEVELOP_FAILED;
+            }
+        }
+    } else {
+        for (index, report) in reports.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            print!("{}", report);
+        }
+    }
+    code
+}
@@ -1,13 +1,21 @@
 pub mod api;
 pub mod argument;
+pub mod catalog;
 pub mod command;
+pub mod config;
 pub mod entity;
 pub mod film;
+pub mod inspect;
+pub mod pipeline;
 pub mod server;
 pub mod utility;
+pub mod watch;
 
-use argument::Arguments;
+use std::process::ExitCode;
+
+use argument::{Arguments, Command};
 use clap::Parser;
+use config::Config;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 fn setup_normal_logging() {
@@ -25,20 +33,80 @@ fn setup_tracing() {
         .init();
 }
 
+fn apply_preset(args: &mut argument::BatchArguments, config: &Config) -> Result<(), String> {
+    let preset = config.resolve(args.presets.preset.as_deref())?;
+    args.apply(&preset);
+    Ok(())
+}
+
 #[tokio::main]
-async fn main() {
-    let args = Arguments::parse();
-    println!("using input arguments: {}", args);
-
-    if args.mode.to_lowercase() == "server" {
-        setup_tracing();
-        let res = server::run(args.clone()).await;
-        if let Err(e) = res {
-            tracing::error!("failed to run in server mode, cause: {}", e);
+async fn main() -> ExitCode {
+    let mut args = Arguments::parse();
+
+    // load the config, flags take precedence over the preset picked from it
+    let config = match args.command.presets() {
+        Some(presets) => match Config::load(presets.config.as_deref()) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("failed to load config, cause: {}", e);
+                return ExitCode::from(command::EXIT_SETUP_FAILED);
+            }
+        },
+        None => Config::default(),
+    };
+    let batch = match &mut args.command {
+        Command::Develop(develop) => Some(&mut develop.batch),
+        Command::Watch(watch) => Some(&mut watch.batch),
+        _ => None,
+    };
+    if let Some(batch) = batch {
+        if let Err(e) = apply_preset(batch, &config) {
+            eprintln!("{}", e);
+            return ExitCode::from(command::EXIT_SETUP_FAILED);
         }
-        return;
+    }
+    if let Command::Inspect(inspect) = &mut args.command {
+        match config.resolve(inspect.presets.preset.as_deref()) {
+            Ok(preset) => inspect.apply(&preset),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(command::EXIT_SETUP_FAILED);
+            }
+        }
+    }
+
+    if matches!(
+        args.command,
+        Command::Develop(_) | Command::Serve(_) | Command::Watch(_)
+    ) {
+        println!("using input arguments: {}", args);
     }
 
-    setup_normal_logging();
-    command::run(args).await;
+    match args.command {
+        Command::Serve(args) => {
+            setup_tracing();
+            if let Err(e) = server::run(args, config).await {
+                tracing::error!("failed to run in server mode, cause: {}", e);
+                return ExitCode::FAILURE;
+            }
+            ExitCode::SUCCESS
+        }
+        Command::Develop(args) => {
+            setup_normal_logging();
+            ExitCode::from(command::run(args).await)
+        }
+        Command::Watch(args) => {
+            setup_normal_logging();
+            ExitCode::from(watch::run(args).await)
+        }
+        Command::Inspect(args) => {
+            setup_normal_logging();
+            ExitCode::from(inspect::run(args))
+        }
+        Command::Painters => ExitCode::from(catalog::painters()),
+        Command::Logos(args) => {
+            setup_normal_logging();
+            ExitCode::from(catalog::logos(args))
+        }
+    }
 }
@@ -0,0 +1,268 @@
+use std::{error::Error, fs, str::FromStr, sync::Arc};
+
+use image::{imageops::crop_imm, GenericImage, RgbImage};
+use log::{debug, warn};
+use moxcms::{ColorProfile, DataColorSpace, Layout, Transform8BitExecutor, TransformOptions};
+
+use crate::film::paint::{
+    constant::{PROFILE_KEEP, PROFILE_P3, PROFILE_SRGB},
+    layout::RegionKind,
+};
+
+use super::{
+    error::{PipelineError, Stage},
+    hook::Hook,
+    stage::Painted,
+};
+
+/// Bytes of the creation date & time in the header of ICC profiles.
+const ICC_DATE_TIME: std::ops::Range<usize> = 24..36;
+
+/// An ICC profile to convert images into, kept with its bytes to embed into the outputs.
+#[derive(Debug, Clone)]
+pub struct IccProfile {
+    profile: ColorProfile,
+    data: Vec<u8>,
+}
+
+impl IccProfile {
+    /// Read the ICC profile from file, e.g. the one of a printer & paper given by the lab.
+    pub fn read(path: &str) -> Result<Self, Box<dyn Error>> {
+        let data = match fs::read(path) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("cannot read ICC profile {}, cause: {}", path, e).into()),
+        };
+        let profile = match ColorProfile::new_from_slice(&data) {
+            Ok(p) => p,
+            Err(e) => return Err(format!("invalid ICC profile {}, cause: {}", path, e).into()),
+        };
+        Ok(IccProfile { profile, data })
+    }
+
+    /// A built-in profile, encoded to be embedded into the outputs.
+    fn built_in(profile: ColorProfile) -> Result<Self, String> {
+        let mut data = profile
+            .encode()
+            .map_err(|e| format!("cannot encode ICC profile, cause: {}", e))?;
+        // clear the creation time in the header, so the same photo is always developed the same
+        data[ICC_DATE_TIME].fill(0);
+        Ok(IccProfile { profile, data })
+    }
+
+    /// Bytes of the profile to embed into the outputs.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Convert the image from its embedded profile into this one.
+    pub fn convert(&self, image: &mut RgbImage, source: Option<&[u8]>) -> Result<(), String> {
+        let transform = create_transform(&source_profile(source), &self.profile)?;
+        *image = apply_transform(&transform, image)?;
+        debug!("converted image of {}x{} into the ICC profile", image.width(), image.height());
+        Ok(())
+    }
+}
+
+/// Profile of the image by its embedded ICC profile, sRGB if there is none or it's not usable.
+pub fn source_profile(icc_profile: Option<&[u8]>) -> ColorProfile {
+    match icc_profile.map(rgb_profile) {
+        Some(Ok(p)) => p,
+        Some(Err(e)) => {
+            warn!("cannot convert from the embedded ICC profile, take it as sRGB, cause: {}", e);
+            ColorProfile::new_srgb()
+        }
+        None => ColorProfile::new_srgb(),
+    }
+}
+
+/// Read the embedded ICC profile, which must be of RGB to convert the pixels from, not CMYK.
+fn rgb_profile(data: &[u8]) -> Result<ColorProfile, String> {
+    let profile = ColorProfile::new_from_slice(data)
+        .map_err(|e| format!("cannot read ICC profile, cause: {}", e))?;
+    match profile.color_space {
+        DataColorSpace::Rgb => Ok(profile),
+        c => Err(format!("ICC profile of {:?} cannot be used on RGB pixels", c)),
+    }
+}
+
+fn create_transform(
+    from: &ColorProfile,
+    to: &ColorProfile,
+) -> Result<Arc<Transform8BitExecutor>, String> {
+    from.create_transform_8bit(Layout::Rgb, to, Layout::Rgb, TransformOptions::default())
+        .map_err(|e| format!("cannot convert between ICC profiles, cause: {}", e))
+}
+
+fn apply_transform(
+    transform: &Arc<Transform8BitExecutor>,
+    image: &RgbImage,
+) -> Result<RgbImage, String> {
+    let mut converted = RgbImage::new(image.width(), image.height());
+    transform
+        .transform(image.as_raw(), &mut converted)
+        .map_err(|e| format!("cannot convert between ICC profiles, cause: {}", e))?;
+    Ok(converted)
+}
+
+/// ICC profile of the outputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputProfile {
+    /// convert everything into sRGB
+    Srgb,
+    /// convert everything into Display P3
+    DisplayP3,
+    /// keep the profile of the photo, converting the frame into it
+    #[default]
+    Keep,
+}
+
+impl FromStr for OutputProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            PROFILE_SRGB => Ok(OutputProfile::Srgb),
+            PROFILE_P3 => Ok(OutputProfile::DisplayP3),
+            PROFILE_KEEP => Ok(OutputProfile::Keep),
+            _ => Err(format!(
+                "unknown output profile `{}`, use {}, {} or {}",
+                s, PROFILE_SRGB, PROFILE_P3, PROFILE_KEEP
+            )),
+        }
+    }
+}
+
+/// Convert the painted photo & its frame into the output profile.
+///
+/// The photo is in the profile embedded into it while the frame, text & logos are painted in
+/// sRGB, so each of them is converted from its own profile by the regions the painter painted.
+pub struct ColorManagement {
+    output: OutputProfile,
+    /// profile to convert into, none to keep the one of the photo
+    target: Option<IccProfile>,
+}
+
+impl ColorManagement {
+    pub fn new(output: OutputProfile) -> Result<Self, String> {
+        let target = match output {
+            OutputProfile::Srgb => Some(IccProfile::built_in(ColorProfile::new_srgb())?),
+            OutputProfile::DisplayP3 => Some(IccProfile::built_in(ColorProfile::new_display_p3())?),
+            OutputProfile::Keep => None,
+        };
+        Ok(ColorManagement { output, target })
+    }
+}
+
+impl Hook for ColorManagement {
+    fn post_paint(&self, painted: &mut Painted) -> Result<(), PipelineError> {
+        // a broken profile is dropped rather than failing the photo, which is taken as sRGB then
+        if let Some(Err(e)) = painted.icc_profile.as_deref().map(rgb_profile) {
+            warn!("drop the embedded ICC profile, take the photo as sRGB, cause: {}", e);
+            painted.icc_profile = None;
+        }
+        let source = painted.icc_profile.as_deref();
+        // the photo is in its embedded profile, sRGB if there is none, the frame is in sRGB
+        let (photo_done, frame_done) = match (&self.target, source) {
+            (None, None) => (true, true),
+            (None, Some(_)) => (true, false),
+            (Some(target), Some(s)) => (s == target.data(), self.output == OutputProfile::Srgb),
+            (Some(_), None) => (
+                self.output == OutputProfile::Srgb,
+                self.output == OutputProfile::Srgb,
+            ),
+        };
+        let target = match &self.target {
+            Some(t) => t.profile.clone(),
+            None => source_profile(source),
+        };
+        let transform = |done: bool, from: ColorProfile| match done {
+            true => Ok(None),
+            false => create_transform(&from, &target)
+                .map(Some)
+                .map_err(|e| PipelineError::new(Stage::Paint, e)),
+        };
+        let photo = transform(photo_done, source_profile(source))?;
+        let frame = transform(frame_done, ColorProfile::new_srgb())?;
+        if let Some(target) = &self.target {
+            painted.icc_profile = Some(target.data().to_vec());
+        }
+        if photo.is_none() && frame.is_none() {
+            return Ok(());
+        }
+        debug!(
+            "convert painted image into {:?} profile, photo: {}, frame: {}",
+            self.output,
+            photo.is_some(),
+            frame.is_some()
+        );
+
+        // convert the frame, then put the photo back converted from its own profile
+        let mut converted = match &frame {
+            Some(t) => apply_transform(t, &painted.image)
+                .map_err(|e| PipelineError::new(Stage::Paint, e))?,
+            None => painted.image.clone(),
+        };
+        let photo_regions = painted
+            .layout
+            .regions()
+            .iter()
+            .filter(|r| r.kind == RegionKind::Photo);
+        for region in photo_regions {
+            let origin =
+                crop_imm(&painted.image, region.x, region.y, region.width, region.height).to_image();
+            let origin = match &photo {
+                Some(t) => {
+                    apply_transform(t, &origin).map_err(|e| PipelineError::new(Stage::Paint, e))?
+                }
+                None => origin,
+            };
+            converted
+                .copy_from(&origin, region.x, region.y)
+                .map_err(|e| PipelineError::new(Stage::Paint, e))?;
+        }
+        painted.image = converted;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use crate::pipeline::stage::fixture::painted;
+
+    use super::*;
+
+    /// Bytes of the data color space in the header of ICC profiles.
+    const ICC_COLOR_SPACE: std::ops::Range<usize> = 16..20;
+
+    fn cmyk_profile() -> Vec<u8> {
+        let mut data = ColorProfile::new_srgb().encode().unwrap();
+        data[ICC_COLOR_SPACE].copy_from_slice(b"CMYK");
+        data
+    }
+
+    #[test]
+    fn broken_embedded_profile_is_taken_as_srgb() {
+        let photo = RgbImage::from_pixel(4, 4, Rgb([200, 100, 50]));
+        for broken in [vec![1, 2, 3], cmyk_profile()] {
+            for output in [OutputProfile::Srgb, OutputProfile::DisplayP3, OutputProfile::Keep] {
+                let color = ColorManagement::new(output).unwrap();
+                let mut broken = painted(photo.clone(), Some(broken.clone()));
+                color.post_paint(&mut broken).unwrap();
+                let mut srgb = painted(photo.clone(), None);
+                color.post_paint(&mut srgb).unwrap();
+                assert_eq!(broken.image, srgb.image, "{:?}", output);
+                assert_eq!(broken.icc_profile, srgb.icc_profile, "{:?}", output);
+            }
+        }
+    }
+
+    #[test]
+    fn output_profile_is_parsed() {
+        assert_eq!("sRGB".parse::<OutputProfile>(), Ok(OutputProfile::Srgb));
+        assert_eq!("p3".parse::<OutputProfile>(), Ok(OutputProfile::DisplayP3));
+        assert_eq!("keep".parse::<OutputProfile>(), Ok(OutputProfile::Keep));
+        assert!("adobe".parse::<OutputProfile>().is_err());
+    }
+}
@@ -0,0 +1,51 @@
+use std::{error::Error, fmt::Display};
+
+/// Stages of the develop pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Exif,
+    Decode,
+    Paint,
+    Encode,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Stage::Exif => "read EXIF",
+            Stage::Decode => "decode image",
+            Stage::Paint => "paint image",
+            Stage::Encode => "encode image",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Error raised by the develop pipeline, telling which stage it fails at.
+#[derive(Debug)]
+pub struct PipelineError {
+    pub stage: Stage,
+    pub cause: String,
+}
+
+impl PipelineError {
+    pub fn new(stage: Stage, cause: impl Display) -> Self {
+        PipelineError {
+            stage,
+            cause: cause.to_string(),
+        }
+    }
+
+    /// Whether the error is caused by the input photo rather than rustant-film itself.
+    pub fn is_bad_input(&self) -> bool {
+        matches!(self.stage, Stage::Exif | Stage::Decode)
+    }
+}
+
+impl Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot {}, cause: {}", self.stage, self.cause)
+    }
+}
+
+impl Error for PipelineError {}
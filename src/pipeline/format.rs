@@ -0,0 +1,414 @@
+use std::str::FromStr;
+
+use image::{
+    imageops::{crop_imm, resize, FilterType},
+    GenericImage,
+};
+use log::{debug, warn};
+
+use crate::{
+    entity::Position,
+    film::paint::{
+        constant::{
+            FORMAT_INSTAX_MINI, FORMAT_INSTAX_SQUARE, FORMAT_INSTAX_WIDE, FORMAT_POLAROID_I_TYPE,
+            WHITE, WINDOW_CROP, WINDOW_FIT,
+        },
+        create_canvas,
+        frame::{Frame, PaddingSize},
+    },
+};
+
+use super::{
+    error::{PipelineError, Stage},
+    hook::Hook,
+    stage::{Decoded, Painted},
+};
+
+/// Print resolution of the formats if not set.
+pub const DEFAULT_DPI: u32 = 300;
+/// Print resolutions allowed, in dots per inch.
+pub const DPI_RANGE: std::ops::RangeInclusive<u32> = 72..=2400;
+const MM_PER_INCH: f32 = 25.4;
+
+/// Physical size of a real instant film in mm, upright with the thick border at the bottom.
+#[derive(Debug)]
+pub struct FilmFormat {
+    pub name: &'static str,
+    /// width & height of the card
+    pub card: (f32, f32),
+    /// width & height of the image window
+    pub window: (f32, f32),
+    /// border above the image window, the left & right ones are even and the rest is at the
+    /// bottom
+    pub top: f32,
+}
+
+pub const FILM_FORMATS: [FilmFormat; 4] = [
+    FilmFormat {
+        name: FORMAT_INSTAX_MINI,
+        card: (54.0, 86.0),
+        window: (46.0, 62.0),
+        top: 6.5,
+    },
+    FilmFormat {
+        name: FORMAT_INSTAX_SQUARE,
+        card: (72.0, 86.0),
+        window: (62.0, 62.0),
+        top: 7.0,
+    },
+    FilmFormat {
+        name: FORMAT_INSTAX_WIDE,
+        card: (108.0, 86.0),
+        window: (99.0, 62.0),
+        top: 7.0,
+    },
+    FilmFormat {
+        name: FORMAT_POLAROID_I_TYPE,
+        card: (88.0, 107.0),
+        window: (79.0, 79.0),
+        top: 6.0,
+    },
+];
+
+impl FilmFormat {
+    pub fn by_name(name: &str) -> Result<&'static FilmFormat, String> {
+        FILM_FORMATS
+            .iter()
+            .find(|f| f.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                format!(
+                    "unknown format `{}`, use one of [{}]",
+                    name,
+                    FILM_FORMATS.map(|f| f.name).join(", ")
+                )
+            })
+    }
+
+    /// Card, window & borders (top, bottom, left, right) in mm, with the film turned so its
+    /// thick border is on the side.
+    fn turned(&self, side: Position) -> ((f32, f32), (f32, f32), [f32; 4]) {
+        let ((card_width, card_height), (window_width, window_height)) = (self.card, self.window);
+        let (top, sides) = (self.top, (card_width - window_width) / 2.0);
+        let bottom = card_height - window_height - top;
+        match side {
+            Position::TOP => (self.card, self.window, [bottom, top, sides, sides]),
+            // turned counterclockwise, the bottom goes right
+            Position::RIGHT => (
+                (card_height, card_width),
+                (window_height, window_width),
+                [sides, sides, top, bottom],
+            ),
+            // turned clockwise, the bottom goes left
+            Position::LEFT => (
+                (card_height, card_width),
+                (window_height, window_width),
+                [sides, sides, bottom, top],
+            ),
+            _ => (self.card, self.window, [top, bottom, sides, sides]),
+        }
+    }
+}
+
+/// How the photo is put into the image window of a film format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowFit {
+    /// fill the window, cutting the photo around the center
+    #[default]
+    Crop,
+    /// keep the whole photo in the window, with blank bars around
+    Fit,
+}
+
+impl FromStr for WindowFit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            WINDOW_CROP => Ok(WindowFit::Crop),
+            WINDOW_FIT => Ok(WindowFit::Fit),
+            _ => Err(format!(
+                "unknown format fit `{}`, use {} or {}",
+                s, WINDOW_CROP, WINDOW_FIT
+            )),
+        }
+    }
+}
+
+/// Develop the photo as a real instant film, at its physical size when printed at the DPI.
+///
+/// The film is turned so its thick border is on the side the painter paints the EXIF info in,
+/// and the photo is cropped or fitted into its image window before painting.
+pub struct Format {
+    film: &'static FilmFormat,
+    side: Position,
+    fit: WindowFit,
+    dpi: u32,
+}
+
+impl Format {
+    pub fn new(film: &'static FilmFormat, side: Position, fit: WindowFit, dpi: u32) -> Self {
+        Format {
+            film,
+            side,
+            fit,
+            dpi,
+        }
+    }
+
+    /// Borders of the film for the painter, relative to the image window so they follow
+    /// downscaled previews, and fixed so the film keeps its size.
+    ///
+    /// The borders are rounded in pixels at the DPI with the rest going to the thick one, so the
+    /// card comes out at its exact size.
+    pub fn frame(&self) -> Frame {
+        let ((card_width, card_height), (window_width, window_height), [top, bottom, left, right]) =
+            self.film.turned(self.side);
+        let (window_width, window_height) = (self.pixels(window_width), self.pixels(window_height));
+        let (border_width, border_height) = (
+            self.pixels(card_width).saturating_sub(window_width),
+            self.pixels(card_height).saturating_sub(window_height),
+        );
+        // the thinner one of each pair is rounded on its own
+        let split = |first: f32, second: f32, total: u32| match first <= second {
+            true => {
+                let first = std::cmp::min(self.pixels_or_zero(first), total);
+                (first, total - first)
+            }
+            false => {
+                let second = std::cmp::min(self.pixels_or_zero(second), total);
+                (total - second, second)
+            }
+        };
+        let (top, bottom) = split(top, bottom, border_height);
+        let (left, right) = split(left, right, border_width);
+        let long_side = window_width.max(window_height) as f32;
+        let [top, bottom, left, right] =
+            [top, bottom, left, right].map(|px| Some(PaddingSize::Long(px as f32 / long_side)));
+        Frame {
+            top,
+            bottom,
+            left,
+            right,
+            fixed: true,
+        }
+    }
+
+    fn pixels(&self, mm: f32) -> u32 {
+        std::cmp::max(self.pixels_or_zero(mm), 1)
+    }
+
+    fn pixels_or_zero(&self, mm: f32) -> u32 {
+        (mm / MM_PER_INCH * self.dpi as f32).round() as u32
+    }
+}
+
+impl Hook for Format {
+    fn pre_paint(&self, decoded: &mut Decoded) -> Result<(), PipelineError> {
+        let (_, (window_width, window_height), _) = self.film.turned(self.side);
+        let (target_width, target_height) = (self.pixels(window_width), self.pixels(window_height));
+        let (width, height) = decoded.image.dimensions();
+        debug!(
+            "put image of {}x{} into the window of {} at {}x{} by {:?}",
+            width, height, self.film.name, target_width, target_height, self.fit
+        );
+
+        let (target_ratio, ratio) = (
+            target_width as f64 / target_height as f64,
+            width as f64 / height as f64,
+        );
+        decoded.image = match self.fit {
+            WindowFit::Crop => {
+                // cut the photo to the ratio of the window around the center
+                let (crop_width, crop_height) = match ratio > target_ratio {
+                    true => ((height as f64 * target_ratio).round() as u32, height),
+                    false => (width, (width as f64 / target_ratio).round() as u32),
+                };
+                let (crop_width, crop_height) =
+                    (crop_width.clamp(1, width), crop_height.clamp(1, height));
+                let cropped = crop_imm(
+                    &decoded.image,
+                    (width - crop_width) / 2,
+                    (height - crop_height) / 2,
+                    crop_width,
+                    crop_height,
+                )
+                .to_image();
+                resize(&cropped, target_width, target_height, FilterType::Lanczos3)
+            }
+            WindowFit::Fit => {
+                let (fit_width, fit_height) = match ratio > target_ratio {
+                    true => (target_width, (target_width as f64 / ratio).round() as u32),
+                    false => ((target_height as f64 * ratio).round() as u32, target_height),
+                };
+                let (fit_width, fit_height) = (
+                    fit_width.clamp(1, target_width),
+                    fit_height.clamp(1, target_height),
+                );
+                let resized = resize(&decoded.image, fit_width, fit_height, FilterType::Lanczos3);
+                let mut window = create_canvas(target_width, target_height, WHITE);
+                window
+                    .copy_from(
+                        &resized,
+                        (target_width - fit_width) / 2,
+                        (target_height - fit_height) / 2,
+                    )
+                    .map_err(|e| PipelineError::new(Stage::Paint, e))?;
+                window
+            }
+        };
+        Ok(())
+    }
+
+    fn post_paint(&self, painted: &mut Painted) -> Result<(), PipelineError> {
+        // the painted image follows previews, so the resolution is taken from it on both axes
+        let ((card_width, card_height), _, _) = self.film.turned(self.side);
+        let (width, height) = painted.image.dimensions();
+        let (dpi_x, dpi_y) = (
+            (width as f32 / card_width * MM_PER_INCH).round() as u32,
+            (height as f32 / card_height * MM_PER_INCH).round() as u32,
+        );
+        // if the painter made a side larger than the card, the whole image is printed within it
+        let dpi = dpi_x.max(dpi_y);
+        if dpi_x.abs_diff(dpi_y) > 1 {
+            warn!(
+                "{} painted into {}x{} does not match its card of {}x{} mm, print it at {} DPI",
+                self.film.name, width, height, card_width, card_height, dpi
+            );
+        }
+        debug!(
+            "{} of {}x{} mm painted into {}x{} at {} DPI",
+            self.film.name,
+            card_width,
+            card_height,
+            painted.image.width(),
+            painted.image.height(),
+            dpi
+        );
+        painted.dpi = Some(dpi);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use image::{Rgb, RgbImage};
+
+    use crate::{
+        entity::Padding,
+        film::{
+            font::fixture,
+            logo::fixture::wide_logo_cache,
+            paint::{create_painter, main_side, text::Typography, PAINTERS},
+        },
+        pipeline::{
+            stage::fixture::{decoded, painted},
+            Pipeline,
+        },
+    };
+
+    use super::*;
+
+    const SIDES: [Position; 4] = [
+        Position::TOP,
+        Position::BOTTOM,
+        Position::LEFT,
+        Position::RIGHT,
+    ];
+
+    #[test]
+    fn frame_makes_the_card_at_its_size() {
+        for film in FILM_FORMATS.iter() {
+            for side in SIDES {
+                let format = Format::new(film, side, WindowFit::Crop, DEFAULT_DPI);
+                let ((card_width, card_height), (window_width, window_height), _) =
+                    film.turned(side);
+                let (width, height) = (format.pixels(window_width), format.pixels(window_height));
+                let mut padding = Padding::new(0, 0, 0, 0);
+                format.frame().apply(&mut padding, width, height, 1.0);
+                assert_eq!(
+                    (
+                        width + padding.left + padding.right,
+                        height + padding.top + padding.bottom
+                    ),
+                    (format.pixels(card_width), format.pixels(card_height)),
+                    "{} turned to {:?}",
+                    film.name,
+                    side
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn thick_border_is_turned_to_the_side() {
+        let film = FilmFormat::by_name(FORMAT_INSTAX_MINI).unwrap();
+        // borders are top, bottom, left & right
+        for (side, thick) in SIDES.into_iter().zip(0..4) {
+            let (_, _, borders) = film.turned(side);
+            let thickest = (0..4)
+                .max_by(|a, b| borders[*a].total_cmp(&borders[*b]))
+                .unwrap();
+            assert_eq!(thickest, thick, "turned to {:?}", side);
+        }
+    }
+
+    #[test]
+    fn dpi_is_taken_from_the_painted_card() {
+        let film = FilmFormat::by_name(FORMAT_INSTAX_WIDE).unwrap();
+        let format = Format::new(film, Position::BOTTOM, WindowFit::Crop, DEFAULT_DPI);
+        let (width, height) = (format.pixels(film.card.0), format.pixels(film.card.1));
+        for (width, height, dpi) in [(width, height, DEFAULT_DPI), (width / 2, height / 2, 150)] {
+            let mut painted = painted(RgbImage::new(width, height), None);
+            format.post_paint(&mut painted).unwrap();
+            assert_eq!(painted.dpi, Some(dpi));
+        }
+        // a side painted larger than the card is printed within it
+        let mut painted = painted(RgbImage::new(width, height * 2), None);
+        format.post_paint(&mut painted).unwrap();
+        assert_eq!(painted.dpi, Some(DEFAULT_DPI * 2));
+    }
+
+    #[test]
+    fn every_painter_paints_the_exact_card() {
+        // a low resolution keeps the test fast, the borders are rounded all the same
+        let dpi = 150;
+        let (fonts, cache) = (fixture::font_set(), wide_logo_cache());
+        for film in FILM_FORMATS.iter() {
+            for (painter, _) in PAINTERS {
+                let side = main_side(Some(painter), None);
+                let format = Format::new(film, side, WindowFit::Crop, dpi);
+                let painter = create_painter(
+                    Some(painter.to_string()),
+                    fonts.clone(),
+                    cache.clone(),
+                    None,
+                    false,
+                    Typography::default(),
+                    format.frame(),
+                );
+                let name = format!("{} by {}", film.name, painter.name());
+                let pipeline = Pipeline::new(Arc::from(painter)).with_hook(Arc::new(format));
+                let photo = RgbImage::from_pixel(300, 200, Rgb([40, 80, 120]));
+                let painted = pipeline.paint(decoded(photo)).unwrap();
+
+                let ((card_width, card_height), _, _) = film.turned(side);
+                let format = Format::new(film, side, WindowFit::Crop, dpi);
+                assert_eq!(
+                    painted.image.dimensions(),
+                    (format.pixels(card_width), format.pixels(card_height)),
+                    "{}",
+                    name
+                );
+                assert_eq!(painted.dpi, Some(dpi), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_format_is_rejected() {
+        assert!(FilmFormat::by_name("Instax-Mini").is_ok());
+        assert!(FilmFormat::by_name("instax-micro").is_err());
+    }
+}
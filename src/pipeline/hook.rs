@@ -0,0 +1,30 @@
+use super::{
+    error::PipelineError,
+    stage::{Decoded, Loaded, Painted},
+};
+
+/// Hook that gets called between the stages of a `Pipeline`.
+///
+/// Every method has a no-op default, so a hook only needs to implement the stages it cares about.
+/// Returning an error from any of them aborts the pipeline.
+pub trait Hook: Send + Sync {
+    /// Called after EXIF is read and before the image gets decoded.
+    fn pre_decode(&self, _loaded: &mut Loaded) -> Result<(), PipelineError> {
+        Ok(())
+    }
+
+    /// Called after the image is decoded and before the painter starts.
+    fn pre_paint(&self, _decoded: &mut Decoded) -> Result<(), PipelineError> {
+        Ok(())
+    }
+
+    /// Called right after the painter finishes.
+    fn post_paint(&self, _painted: &mut Painted) -> Result<(), PipelineError> {
+        Ok(())
+    }
+
+    /// Called after all `post_paint` hooks and before the image gets encoded.
+    fn pre_encode(&self, _painted: &mut Painted) -> Result<(), PipelineError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+pub mod color;
+pub mod error;
+pub mod format;
+pub mod hook;
+pub mod preview;
+pub mod print;
+pub mod rendition;
+pub mod stage;
+
+use std::{
+    borrow::Cow,
+    io::{BufReader, Cursor},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use exif::Reader;
+use image::{
+    codecs::{
+        jpeg::{JpegEncoder, PixelDensity},
+        png::PngEncoder,
+        webp::WebPEncoder,
+    },
+    error::EncodingError,
+    imageops::{overlay, resize, FilterType},
+    ColorType, ImageEncoder, ImageError, ImageFormat, RgbImage,
+};
+use log::{debug, warn};
+use tiff::{
+    encoder::{colortype, Rational, TiffEncoder, TiffValue},
+    tags::{ResolutionUnit, Tag, Type},
+    TiffResult,
+};
+
+use crate::{
+    entity::{Caption, ExifInfo},
+    film::paint::{constant::WHITE, create_canvas, layout::Layout, Painter},
+    utility::decode::get_decoder,
+};
+
+pub use color::{ColorManagement, IccProfile, OutputProfile};
+pub use error::{PipelineError, Stage};
+pub use format::{FilmFormat, Format, WindowFit};
+pub use hook::Hook;
+pub use preview::{DebugLayout, Downscale};
+pub use print::{Length, Print, PrintSize};
+pub use rendition::{Rendition, RenditionFormat, RenditionSize};
+pub use stage::{Decoded, Encoded, Loaded, Painted};
+
+/// The develop pipeline shared by command & server mode.
+///
+/// It runs a photo through the following stages:
+///
+/// ```txt
+/// bytes -> [load] -> Loaded -> [decode] -> Decoded -> [paint] -> Painted -> [encode] -> Encoded
+///                  |                      |                    |                     |
+///              pre_decode             pre_paint           post_paint            pre_encode
+/// ```
+///
+/// Each stage can be called on its own, or use `run` to go through all of them.
+pub struct Pipeline {
+    painter: Arc<dyn Painter>,
+    hooks: Vec<Arc<dyn Hook>>,
+}
+
+impl Pipeline {
+    pub fn new(painter: Arc<dyn Painter>) -> Self {
+        Pipeline {
+            painter,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Name of the painter used by this pipeline.
+    pub fn painter_name(&self) -> &str {
+        self.painter.name()
+    }
+
+    /// Append a hook, hooks are called in the order they are added.
+    pub fn with_hook(mut self, hook: Arc<dyn Hook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Read EXIF from the photo bytes, which is painted along with the caption.
+    pub fn load(&self, data: Bytes, caption: Caption) -> Result<Loaded, PipelineError> {
+        let cursor = Cursor::new(&data);
+        let mut reader = BufReader::new(cursor);
+        let exif = Reader::new()
+            .read_from_container(&mut reader)
+            .map_err(|e| PipelineError::new(Stage::Exif, e))?;
+        let exif_info = ExifInfo::new(&exif);
+        debug!("loaded exif info: {}", exif_info);
+
+        let mut loaded = Loaded {
+            data,
+            exif_info,
+            caption,
+        };
+        for hook in &self.hooks {
+            hook.pre_decode(&mut loaded)?;
+        }
+        Ok(loaded)
+    }
+
+    /// Decode the photo bytes into a `RgbImage` with its ICC profile.
+    pub fn decode(&self, loaded: Loaded) -> Result<Decoded, PipelineError> {
+        let Loaded {
+            data,
+            exif_info,
+            caption,
+        } = loaded;
+        let mut decoder = get_decoder(data).map_err(|e| PipelineError::new(Stage::Decode, e))?;
+
+        // get the potential ICC
+        let icc_profile = decoder
+            .icc_profile()
+            .map_err(|e| PipelineError::new(Stage::Decode, e))?;
+        if icc_profile.is_none() {
+            debug!("no embedding ICC profile");
+        }
+
+        // decode the image into RgbImage
+        let (width, height) = decoder.dimensions();
+        let color_type = decoder.color_type();
+        if color_type != image::ColorType::Rgb8 {
+            return Err(PipelineError::new(
+                Stage::Decode,
+                format!("cannot handle color type {:?}", color_type),
+            ));
+        }
+        let total_bytes = decoder.total_bytes() as usize;
+        let mut buffer = vec![0u8; total_bytes];
+        decoder
+            .read_image_boxed(&mut buffer)
+            .map_err(|e| PipelineError::new(Stage::Decode, e))?;
+        let image = image::RgbImage::from_raw(width, height, buffer).ok_or_else(|| {
+            PipelineError::new(Stage::Decode, "decoded buffer does not fit the dimensions")
+        })?;
+
+        Ok(Decoded {
+            exif_info,
+            caption,
+            image,
+            color_type,
+            icc_profile,
+            scale: 1.0,
+        })
+    }
+
+    /// Paint the instant-film layout on the decoded image.
+    pub fn paint(&self, mut decoded: Decoded) -> Result<Painted, PipelineError> {
+        for hook in &self.hooks {
+            hook.pre_paint(&mut decoded)?;
+        }
+
+        let Decoded {
+            exif_info,
+            caption,
+            mut image,
+            color_type,
+            icc_profile,
+            scale,
+        } = decoded;
+        let mut layout = Layout::new();
+        self.painter
+            .paint_layout(&mut image, &exif_info, &caption, scale, &mut layout)
+            .map_err(|e| PipelineError::new(Stage::Paint, e))?;
+
+        let mut painted = Painted {
+            exif_info,
+            image,
+            color_type,
+            icc_profile,
+            layout,
+            dpi: None,
+        };
+        for hook in &self.hooks {
+            hook.post_paint(&mut painted)?;
+        }
+        Ok(painted)
+    }
+
+    /// Encode the painted image into JPEG bytes, keeping the origin ICC profile.
+    pub fn encode(&self, painted: Painted) -> Result<Encoded, PipelineError> {
+        let mut encoded = self.encode_renditions(painted, &[Rendition::default()])?;
+        Ok(encoded.remove(0))
+    }
+
+    /// Encode the painted image into each of the renditions, keeping the origin ICC profile.
+    ///
+    /// The `pre_encode` hooks are called only once for all the renditions.
+    pub fn encode_renditions(
+        &self,
+        mut painted: Painted,
+        renditions: &[Rendition],
+    ) -> Result<Vec<Encoded>, PipelineError> {
+        for hook in &self.hooks {
+            hook.pre_encode(&mut painted)?;
+        }
+        renditions
+            .iter()
+            .map(|rendition| encode_rendition(&painted, rendition))
+            .collect()
+    }
+
+    /// Run the photo bytes through all the stages.
+    pub fn run(&self, data: Bytes, caption: Caption) -> Result<Encoded, PipelineError> {
+        let loaded = self.load(data, caption)?;
+        let decoded = self.decode(loaded)?;
+        let painted = self.paint(decoded)?;
+        self.encode(painted)
+    }
+}
+
+fn encode_rendition(painted: &Painted, rendition: &Rendition) -> Result<Encoded, PipelineError> {
+    // resize the image if needed, an exact size fits the whole image in with white around
+    let (width, height) = painted.image.dimensions();
+    let long_side = std::cmp::max(width, height);
+    let scale = |ratio: f64| {
+        (
+            std::cmp::max((width as f64 * ratio).round() as u32, 1),
+            std::cmp::max((height as f64 * ratio).round() as u32, 1),
+        )
+    };
+    let (size, canvas) = match rendition.size {
+        RenditionSize::Full => (None, None),
+        RenditionSize::LongSide(target) if target < long_side => {
+            (Some(scale(target as f64 / long_side as f64)), None)
+        }
+        RenditionSize::LongSide(_) => (None, None),
+        RenditionSize::Exact(w, h) if (w, h) != (width, height) => {
+            let (fit_width, fit_height) =
+                scale(f64::min(w as f64 / width as f64, h as f64 / height as f64));
+            (Some((fit_width.min(w), fit_height.min(h))), Some((w, h)))
+        }
+        RenditionSize::Exact(_, _) => (None, None),
+    };
+    let resized = size.map(|(w, h)| {
+        debug!("resize rendition {} to {}x{}", rendition, w, h);
+        resize(&painted.image, w, h, FilterType::Lanczos3)
+    });
+    let image = resized.as_ref().unwrap_or(&painted.image);
+    // keep the print size of resized renditions
+    let dpi = painted
+        .dpi
+        .map(|dpi| (dpi as u64 * image.width() as u64 / width as u64).clamp(1, u16::MAX as u64));
+    let framed = canvas.map(|(w, h)| {
+        let mut framed = create_canvas(w, h, WHITE);
+        let (x, y) = ((w - image.width()) / 2, (h - image.height()) / 2);
+        overlay(&mut framed, image, x as i64, y as i64);
+        framed
+    });
+    let image = framed.as_ref().unwrap_or(image);
+
+    // encode the image into bytes
+    let mut buffer = Vec::new();
+    let icc_profile = painted.icc_profile.clone();
+    let color_type = painted.color_type;
+    let result = match rendition.format {
+        RenditionFormat::Jpeg => {
+            let mut encoder = JpegEncoder::new_with_quality(&mut buffer, rendition.quality);
+            if let Some(dpi) = dpi {
+                encoder.set_pixel_density(PixelDensity::dpi(dpi as u16));
+            }
+            write_image(encoder, image, color_type, icc_profile)
+        }
+        RenditionFormat::Png => {
+            write_image(PngEncoder::new(&mut buffer), image, color_type, icc_profile)
+        }
+        RenditionFormat::WebP => write_image(
+            WebPEncoder::new_lossless(&mut buffer),
+            image,
+            color_type,
+            icc_profile,
+        ),
+        RenditionFormat::Tiff => {
+            write_tiff(&mut buffer, image, dpi.map(|d| d as u32), icc_profile).map_err(|e| {
+                ImageError::Encoding(EncodingError::new(ImageFormat::Tiff.into(), e))
+            })
+        }
+    };
+    result.map_err(|e| PipelineError::new(Stage::Encode, e))?;
+
+    Ok(Encoded {
+        exif_info: painted.exif_info.clone(),
+        data: buffer,
+        rendition: rendition.clone(),
+    })
+}
+
+fn write_image(
+    mut encoder: impl ImageEncoder,
+    image: &RgbImage,
+    color_type: ColorType,
+    icc_profile: Option<Vec<u8>>,
+) -> image::ImageResult<()> {
+    if let Some(profile) = icc_profile {
+        if let Err(e) = encoder.set_icc_profile(profile) {
+            warn!("cannot set ICC profile to output file which may lead to incorrect color, cause: {}", e);
+        }
+    }
+    encoder.write_image(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        color_type.into(),
+    )
+}
+
+/// Tag of the ICC profile in TIFF files.
+const TIFF_TAG_ICC_PROFILE: u16 = 34675;
+
+/// ICC profile in a TIFF tag, of the undefined type as the TIFF/EP spec requires.
+struct IccTag<'a>(&'a [u8]);
+
+impl TiffValue for IccTag<'_> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: Type = Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.0)
+    }
+}
+
+/// Encode the image into an uncompressed TIFF with the resolution & ICC profile tags, for print.
+fn write_tiff(
+    buffer: &mut Vec<u8>,
+    image: &RgbImage,
+    dpi: Option<u32>,
+    icc_profile: Option<Vec<u8>>,
+) -> TiffResult<()> {
+    let mut cursor = Cursor::new(buffer);
+    let mut encoder = TiffEncoder::new(&mut cursor)?;
+    let mut tiff = encoder.new_image::<colortype::RGB8>(image.width(), image.height())?;
+    if let Some(dpi) = dpi {
+        tiff.resolution(ResolutionUnit::Inch, Rational { n: dpi, d: 1 });
+    }
+    if let Some(profile) = icc_profile {
+        tiff.encoder()
+            .write_tag(Tag::Unknown(TIFF_TAG_ICC_PROFILE), IccTag(&profile))?;
+    }
+    tiff.write_data(image.as_raw())
+}
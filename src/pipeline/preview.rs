@@ -0,0 +1,59 @@
+use image::imageops::{resize, FilterType};
+use log::debug;
+
+use crate::film::paint::layout::draw_layout;
+
+use super::{
+    error::PipelineError,
+    hook::Hook,
+    stage::{Decoded, Painted},
+};
+
+/// Long side of the previews rendered to review layouts.
+pub const LAYOUT_PREVIEW_LONG_SIDE: u32 = 1024;
+
+/// Downscale the decoded image so its long side is at most the given size.
+///
+/// Painters size everything by the long side, so the layout stays the same relative to the image.
+pub struct Downscale {
+    long_side: u32,
+}
+
+impl Downscale {
+    pub fn new(long_side: u32) -> Self {
+        Downscale {
+            long_side: long_side.max(1),
+        }
+    }
+}
+
+impl Hook for Downscale {
+    fn pre_paint(&self, decoded: &mut Decoded) -> Result<(), PipelineError> {
+        let (width, height) = decoded.image.dimensions();
+        let long_side = std::cmp::max(width, height);
+        if long_side <= self.long_side {
+            return Ok(());
+        }
+
+        let ratio = self.long_side as f64 / long_side as f64;
+        let new_width = std::cmp::max((width as f64 * ratio).round() as u32, 1);
+        let new_height = std::cmp::max((height as f64 * ratio).round() as u32, 1);
+        debug!(
+            "downscale image from {}x{} to {}x{}",
+            width, height, new_width, new_height
+        );
+        decoded.image = resize(&decoded.image, new_width, new_height, FilterType::Triangle);
+        decoded.scale *= ratio as f32;
+        Ok(())
+    }
+}
+
+/// Draw colored boxes around the regions the painter painted.
+pub struct DebugLayout;
+
+impl Hook for DebugLayout {
+    fn post_paint(&self, painted: &mut Painted) -> Result<(), PipelineError> {
+        draw_layout(&mut painted.image, &painted.layout);
+        Ok(())
+    }
+}
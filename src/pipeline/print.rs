@@ -0,0 +1,370 @@
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+use image::{
+    imageops::{overlay, resize, FilterType},
+    RgbImage,
+};
+use imageproc::{drawing::draw_filled_rect_mut, rect::Rect};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::film::paint::{
+    constant::{BLACK, WHITE},
+    create_canvas,
+};
+
+use super::{
+    color::IccProfile,
+    error::{PipelineError, Stage},
+    hook::Hook,
+    stage::Painted,
+};
+
+const MM_PER_INCH: f32 = 25.4;
+/// Gap between the bleed & the crop marks in mm.
+const CROP_MARK_GAP: f32 = 2.0;
+/// Length of the crop marks in mm.
+const CROP_MARK_LENGTH: f32 = 5.0;
+/// Width of the crop marks in mm, about 0.25 pt.
+const CROP_MARK_WIDTH: f32 = 0.1;
+/// Largest side of a print in pixels, the limit of JPEG.
+const MAX_PRINT_SIDE: u32 = 65535;
+
+/// Unit of physical lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Inch,
+    Millimeter,
+}
+
+impl Unit {
+    fn mm(&self, value: f32) -> f32 {
+        match self {
+            Unit::Inch => value * MM_PER_INCH,
+            Unit::Millimeter => value,
+        }
+    }
+
+    /// Split a value like `6in` or `152mm` into its number & unit.
+    fn split(s: &str) -> Option<(&str, Unit)> {
+        if let Some(v) = s.strip_suffix("in") {
+            Some((v, Unit::Inch))
+        } else {
+            s.strip_suffix("mm").map(|v| (v, Unit::Millimeter))
+        }
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Inch => write!(f, "in"),
+            Unit::Millimeter => write!(f, "mm"),
+        }
+    }
+}
+
+/// A physical length like `3mm` or `0.125in`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Length {
+    pub value: f32,
+    pub unit: Unit,
+}
+
+impl Length {
+    pub fn mm(&self) -> f32 {
+        self.unit.mm(self.value)
+    }
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let invalid = || {
+            format!(
+                "invalid length `{}`, use millimeters like `3mm` or inches like `0.125in`",
+                s
+            )
+        };
+        let (value, unit) = Unit::split(&s).ok_or_else(invalid)?;
+        match value.trim().parse::<f32>() {
+            Ok(value) if value.is_finite() && value >= 0.0 => Ok(Length { value, unit }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.value, self.unit)
+    }
+}
+
+impl TryFrom<String> for Length {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Length> for String {
+    fn from(value: Length) -> Self {
+        value.to_string()
+    }
+}
+
+/// Physical size of a print like `4x6in` or `102x152mm`, turned to the orientation of the photo.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PrintSize {
+    pub width: f32,
+    pub height: f32,
+    pub unit: Unit,
+}
+
+impl PrintSize {
+    /// Width & height in mm.
+    pub fn mm(&self) -> (f32, f32) {
+        (self.unit.mm(self.width), self.unit.mm(self.height))
+    }
+}
+
+impl FromStr for PrintSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let invalid = || {
+            format!(
+                "invalid print size `{}`, use `<width>x<height>` in inches like `4x6in` or in millimeters like `102x152mm`",
+                s
+            )
+        };
+        let (size, unit) = Unit::split(&s).ok_or_else(invalid)?;
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let side = |v: &str| match v.trim().parse::<f32>() {
+            Ok(v) if v.is_finite() && v > 0.0 => Ok(v),
+            _ => Err(invalid()),
+        };
+        Ok(PrintSize {
+            width: side(width)?,
+            height: side(height)?,
+            unit,
+        })
+    }
+}
+
+impl Display for PrintSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}{}", self.width, self.height, self.unit)
+    }
+}
+
+impl TryFrom<String> for PrintSize {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<PrintSize> for String {
+    fn from(value: PrintSize) -> Self {
+        value.to_string()
+    }
+}
+
+/// Make the painted photo ready for a print lab.
+///
+/// Right before encoding, so after the layout is reviewed, the photo is fitted into the print
+/// size at the DPI, extended by the bleed with its edges, surrounded by crop marks at the trim
+/// lines, and converted into the ICC profile of the printer. The DPI is written into the
+/// outputs, a film format keeps its own unless a print size is set.
+pub struct Print {
+    size: Option<PrintSize>,
+    dpi: u32,
+    bleed: f32,
+    crop_marks: bool,
+    profile: Option<Arc<IccProfile>>,
+}
+
+impl Print {
+    pub fn new(
+        size: Option<PrintSize>,
+        dpi: u32,
+        bleed: Option<Length>,
+        crop_marks: bool,
+        profile: Option<Arc<IccProfile>>,
+    ) -> Self {
+        Print {
+            size,
+            dpi,
+            bleed: bleed.map_or(0.0, |b| b.mm()),
+            crop_marks,
+            profile,
+        }
+    }
+}
+
+impl Hook for Print {
+    fn pre_encode(&self, painted: &mut Painted) -> Result<(), PipelineError> {
+        let dpi = match self.size {
+            Some(_) => self.dpi,
+            None => painted.dpi.unwrap_or(self.dpi),
+        };
+        let pixels = |mm: f32| (mm / MM_PER_INCH * dpi as f32).round() as u32;
+
+        // fit the photo into the print, turned to the orientation of the photo
+        if let Some(size) = self.size {
+            let (width, height) = painted.image.dimensions();
+            let (print_width, print_height) = match size.mm() {
+                (w, h) if (w > h) != (width > height) => (h, w),
+                (w, h) => (w, h),
+            };
+            let (print_width, print_height) =
+                (pixels(print_width).max(1), pixels(print_height).max(1));
+            if std::cmp::max(print_width, print_height) > MAX_PRINT_SIDE {
+                return Err(PipelineError::new(
+                    Stage::Encode,
+                    format!(
+                        "print of {} at {} DPI is {}x{}, larger than {} pixels",
+                        size, dpi, print_width, print_height, MAX_PRINT_SIDE
+                    ),
+                ));
+            }
+            let ratio = f64::min(
+                print_width as f64 / width as f64,
+                print_height as f64 / height as f64,
+            );
+            let (fit_width, fit_height) = (
+                ((width as f64 * ratio).round() as u32).clamp(1, print_width),
+                ((height as f64 * ratio).round() as u32).clamp(1, print_height),
+            );
+            debug!(
+                "fit image of {}x{} into print of {} at {} DPI, {}x{}",
+                width, height, size, dpi, print_width, print_height
+            );
+            let resized = resize(&painted.image, fit_width, fit_height, FilterType::Lanczos3);
+            let mut print = create_canvas(print_width, print_height, WHITE);
+            overlay(
+                &mut print,
+                &resized,
+                ((print_width - fit_width) / 2) as i64,
+                ((print_height - fit_height) / 2) as i64,
+            );
+            painted.image = print;
+        }
+
+        let bleed = pixels(self.bleed);
+        let (gap, length) = (pixels(CROP_MARK_GAP), pixels(CROP_MARK_LENGTH).max(1));
+        let margin = match self.crop_marks {
+            true => gap + length,
+            false => 0,
+        };
+        let (width, height) = painted.image.dimensions();
+        let long_side = std::cmp::max(width, height) as u64 + (bleed as u64 + margin as u64) * 2;
+        if long_side > MAX_PRINT_SIDE as u64 {
+            return Err(PipelineError::new(
+                Stage::Encode,
+                format!(
+                    "print with bleed & crop marks at {} DPI is {} pixels long, larger than {} pixels",
+                    dpi, long_side, MAX_PRINT_SIDE
+                ),
+            ));
+        }
+        if bleed > 0 {
+            painted.image = extend_edges(&painted.image, bleed);
+        }
+        if self.crop_marks {
+            painted.image = add_crop_marks(
+                &painted.image,
+                bleed,
+                gap,
+                length,
+                pixels(CROP_MARK_WIDTH).max(1),
+            );
+        }
+
+        if let Some(profile) = &self.profile {
+            profile
+                .convert(&mut painted.image, painted.icc_profile.as_deref())
+                .map_err(|e| PipelineError::new(Stage::Encode, e))?;
+            painted.icc_profile = Some(profile.data().to_vec());
+        }
+        painted.dpi = Some(dpi);
+        Ok(())
+    }
+}
+
+/// Extend the image by the bleed on every side, repeating its edges.
+fn extend_edges(image: &RgbImage, bleed: u32) -> RgbImage {
+    let (width, height) = image.dimensions();
+    RgbImage::from_fn(width + bleed * 2, height + bleed * 2, |x, y| {
+        let x = x.saturating_sub(bleed).min(width - 1);
+        let y = y.saturating_sub(bleed).min(height - 1);
+        *image.get_pixel(x, y)
+    })
+}
+
+/// Put the image on a white sheet with crop marks at its trim lines, outside the bleed.
+fn add_crop_marks(image: &RgbImage, bleed: u32, gap: u32, length: u32, thickness: u32) -> RgbImage {
+    let (width, height) = image.dimensions();
+    let margin = gap + length;
+    let (sheet_width, sheet_height) = (width + margin * 2, height + margin * 2);
+    let mut sheet = create_canvas(sheet_width, sheet_height, WHITE);
+    overlay(&mut sheet, image, margin as i64, margin as i64);
+
+    // trim lines are inside the bleed
+    let trim_x = [margin + bleed, margin + width - bleed];
+    let trim_y = [margin + bleed, margin + height - bleed];
+    let offset = (thickness / 2) as i32;
+    for x in trim_x {
+        for y in [0, sheet_height - length] {
+            let rect = Rect::at(x as i32 - offset, y as i32).of_size(thickness, length);
+            draw_filled_rect_mut(&mut sheet, rect, BLACK);
+        }
+    }
+    for y in trim_y {
+        for x in [0, sheet_width - length] {
+            let rect = Rect::at(x as i32, y as i32 - offset).of_size(length, thickness);
+            draw_filled_rect_mut(&mut sheet, rect, BLACK);
+        }
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print_size_is_parsed_in_both_units() {
+        let size = "4x6in".parse::<PrintSize>().unwrap();
+        assert_eq!(size.mm(), (4.0 * MM_PER_INCH, 6.0 * MM_PER_INCH));
+        let size = " 102 x 152MM ".parse::<PrintSize>().unwrap();
+        assert_eq!((size.width, size.height, size.unit), (102.0, 152.0, Unit::Millimeter));
+        assert_eq!(size.to_string(), "102x152mm");
+        assert_eq!(size.to_string().parse::<PrintSize>(), Ok(size));
+    }
+
+    #[test]
+    fn invalid_print_size_is_rejected() {
+        for s in ["4x6", "4in", "4x6cm", "0x6in", "-4x6in", "4xinfin", "x6in", ""] {
+            assert!(s.parse::<PrintSize>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn length_is_parsed_in_both_units() {
+        assert_eq!("0.125in".parse::<Length>().unwrap().mm(), 0.125 * MM_PER_INCH);
+        assert_eq!("3mm".parse::<Length>().unwrap().mm(), 3.0);
+        assert_eq!("0mm".parse::<Length>().unwrap().mm(), 0.0);
+        for s in ["3", "3cm", "-3mm", "nanmm", "mm"] {
+            assert!(s.parse::<Length>().is_err(), "{}", s);
+        }
+    }
+}
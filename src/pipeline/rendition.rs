@@ -0,0 +1,289 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Quality of JPEG renditions if not given, same as the encoder's default.
+pub const DEFAULT_QUALITY: u8 = 75;
+
+/// Size of a rendition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenditionSize {
+    /// keep the size of the painted image
+    Full,
+    /// scale so the long side is of this size, never upscale
+    LongSide(u32),
+    /// fit into exactly this width & height keeping the aspect ratio, with white around
+    Exact(u32, u32),
+}
+
+/// Image format of a rendition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenditionFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Tiff,
+}
+
+impl RenditionFormat {
+    /// Format of the extension, like `jpg` or `TIFF`.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(RenditionFormat::Jpeg),
+            "png" => Some(RenditionFormat::Png),
+            "webp" => Some(RenditionFormat::WebP),
+            "tif" | "tiff" => Some(RenditionFormat::Tiff),
+            _ => None,
+        }
+    }
+
+    /// Extension of output files in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RenditionFormat::Jpeg => "jpg",
+            RenditionFormat::Png => "png",
+            RenditionFormat::WebP => "webp",
+            RenditionFormat::Tiff => "tif",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            RenditionFormat::Jpeg => "image/jpeg",
+            RenditionFormat::Png => "image/png",
+            RenditionFormat::WebP => "image/webp",
+            RenditionFormat::Tiff => "image/tiff",
+        }
+    }
+}
+
+/// One of the outputs encoded from a painted image.
+///
+/// It's written like `size=2048,format=jpg,quality=90,suffix=_2048`, where every key is optional:
+///
+/// - `size`: `full`, a long side like `2048`, or an exact size like `1200x800` the image is fitted
+///   into, `full` by default
+/// - `format`: `jpg`, `png`, `webp` or `tif`, `jpg` by default
+/// - `quality`: 1 to 100 for `jpg`, 75 by default
+/// - `suffix`: appended to the output file stem, empty by default
+///
+/// A value without a key is the size, e.g. `full` or `2048,suffix=_2048`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rendition {
+    pub size: RenditionSize,
+    pub format: RenditionFormat,
+    pub quality: u8,
+    pub suffix: String,
+}
+
+impl Default for Rendition {
+    fn default() -> Self {
+        Rendition {
+            size: RenditionSize::Full,
+            format: RenditionFormat::Jpeg,
+            quality: DEFAULT_QUALITY,
+            suffix: String::new(),
+        }
+    }
+}
+
+impl FromStr for Rendition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rendition = Rendition::default();
+        for pair in s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            // a bare value is the size, like `2048`
+            let (key, value) = match pair.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => ("size", pair),
+            };
+            match key {
+                "size" => rendition.size = parse_size(value)?,
+                "format" => {
+                    rendition.format = match RenditionFormat::from_extension(value) {
+                        Some(format) => format,
+                        None => {
+                            return Err(format!(
+                                "unknown rendition format `{}`, use jpg, png, webp or tif",
+                                value
+                            ))
+                        }
+                    }
+                }
+                "quality" => {
+                    rendition.quality = match value.parse::<u8>() {
+                        Ok(q) if (1..=100).contains(&q) => q,
+                        _ => {
+                            return Err(format!(
+                                "rendition quality must be 1 to 100, got `{}`",
+                                value
+                            ))
+                        }
+                    }
+                }
+                "suffix" => {
+                    if value.contains(['/', '\\']) {
+                        return Err(format!(
+                            "rendition suffix cannot hold a path, got `{}`",
+                            value
+                        ));
+                    }
+                    rendition.suffix = value.to_string();
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown rendition key `{}`, use size, format, quality or suffix",
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(rendition)
+    }
+}
+
+fn parse_size(value: &str) -> Result<RenditionSize, String> {
+    let invalid = || {
+        format!(
+            "rendition size must be `full`, `<long side>` or `<width>x<height>`, got `{}`",
+            value
+        )
+    };
+    if value.eq_ignore_ascii_case("full") {
+        return Ok(RenditionSize::Full);
+    }
+    let size = match value.split_once(['x', 'X']) {
+        Some((width, height)) => {
+            let width = width.parse::<u32>().map_err(|_| invalid())?;
+            let height = height.parse::<u32>().map_err(|_| invalid())?;
+            RenditionSize::Exact(width, height)
+        }
+        None => RenditionSize::LongSide(value.parse::<u32>().map_err(|_| invalid())?),
+    };
+    match size {
+        RenditionSize::LongSide(0) | RenditionSize::Exact(0, _) | RenditionSize::Exact(_, 0) => {
+            Err(invalid())
+        }
+        _ => Ok(size),
+    }
+}
+
+impl Display for Rendition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = match self.size {
+            RenditionSize::Full => "full".to_string(),
+            RenditionSize::LongSide(long_side) => long_side.to_string(),
+            RenditionSize::Exact(width, height) => format!("{}x{}", width, height),
+        };
+        write!(
+            f,
+            "size={},format={},quality={},suffix={}",
+            size,
+            self.format.extension(),
+            self.quality,
+            self.suffix
+        )
+    }
+}
+
+impl TryFrom<String> for Rendition {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Rendition> for String {
+    fn from(value: Rendition) -> Self {
+        value.to_string()
+    }
+}
+
+/// Check the renditions can be written to their output filenames, given in the same order.
+///
+/// No two renditions may write to the same file, and each file must end with the extension of
+/// its format, so e.g. a PNG is never written into `photo.jpg`.
+pub fn check_conflicts(renditions: &[Rendition], filenames: &[String]) -> Result<(), String> {
+    for (index, (rendition, filename)) in renditions.iter().zip(filenames).enumerate() {
+        let format = filename
+            .rsplit_once('.')
+            .and_then(|(_, ext)| RenditionFormat::from_extension(ext));
+        if format != Some(rendition.format) {
+            return Err(format!(
+                "rendition {} is written to {}, which does not end with `.{}`, use `{{ext}}` in the filename template",
+                rendition,
+                filename,
+                rendition.format.extension()
+            ));
+        }
+        if let Some(other) = filenames[..index].iter().position(|f| f == filename) {
+            return Err(format!(
+                "renditions {} & {} are both written to {}, tell them apart by the suffix",
+                renditions[other], rendition, filename
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renditions(values: &[&str]) -> Vec<Rendition> {
+        values.iter().map(|v| v.parse().unwrap()).collect()
+    }
+
+    fn filenames(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_rendition() {
+        let rendition: Rendition = "2048,format=webp,quality=90,suffix=_2048".parse().unwrap();
+        assert_eq!(rendition.size, RenditionSize::LongSide(2048));
+        assert_eq!(rendition.format, RenditionFormat::WebP);
+        assert_eq!(rendition.quality, 90);
+        assert_eq!(rendition.suffix, "_2048");
+        assert_eq!(rendition.to_string().parse::<Rendition>().unwrap(), rendition);
+
+        let rendition: Rendition = "size=1200x800,format=TIFF".parse().unwrap();
+        assert_eq!(rendition.size, RenditionSize::Exact(1200, 800));
+        assert_eq!(rendition.format, RenditionFormat::Tiff);
+    }
+
+    #[test]
+    fn reject_invalid_renditions() {
+        for value in ["0", "0x800", "format=gif", "quality=0", "suffix=a/b", "color=red"] {
+            assert!(value.parse::<Rendition>().is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn renditions_written_apart() {
+        let renditions = renditions(&["full", "2048,suffix=_2048", "format=png"]);
+        let filenames = filenames(&["photo.jpg", "photo_2048.jpg", "photo.png"]);
+        assert!(check_conflicts(&renditions, &filenames).is_ok());
+    }
+
+    #[test]
+    fn renditions_written_to_the_same_file() {
+        // the same format & suffix, or a template ignoring them
+        let renditions = renditions(&["full", "2048"]);
+        let filenames = filenames(&["photo.jpg", "photo.jpg"]);
+        assert!(check_conflicts(&renditions, &filenames).is_err());
+    }
+
+    #[test]
+    fn renditions_written_with_another_extension() {
+        // a template like `{stem}.jpg` writes a PNG into a `.jpg` file
+        let renditions = renditions(&["format=png"]);
+        assert!(check_conflicts(&renditions, &filenames(&["photo.jpg"])).is_err());
+        assert!(check_conflicts(&renditions, &filenames(&["photo"])).is_err());
+        let renditions = self::renditions(&["format=jpg"]);
+        assert!(check_conflicts(&renditions, &filenames(&["photo.JPEG"])).is_ok());
+    }
+}
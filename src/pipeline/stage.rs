@@ -0,0 +1,88 @@
+use bytes::Bytes;
+use image::{ColorType, RgbImage};
+
+use crate::{
+    entity::{Caption, ExifInfo},
+    film::paint::layout::Layout,
+};
+
+use super::rendition::Rendition;
+
+/// A photo whose bytes are in memory and whose EXIF has been read, ready to be decoded.
+#[derive(Debug, Clone)]
+pub struct Loaded {
+    pub data: Bytes,
+    pub exif_info: ExifInfo,
+    /// text the user writes on the film
+    pub caption: Caption,
+}
+
+/// A photo decoded into pixels, ready to be painted.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub exif_info: ExifInfo,
+    pub caption: Caption,
+    pub image: RgbImage,
+    pub color_type: ColorType,
+    pub icc_profile: Option<Vec<u8>>,
+    /// size of the image relative to the photo as decoded, below 1 once downscaled for previews
+    pub scale: f32,
+}
+
+/// A photo with the instant-film layout painted on, ready to be encoded.
+#[derive(Debug, Clone)]
+pub struct Painted {
+    pub exif_info: ExifInfo,
+    pub image: RgbImage,
+    pub color_type: ColorType,
+    pub icc_profile: Option<Vec<u8>>,
+    /// regions the painter painted
+    pub layout: Layout,
+    /// print resolution in dots per inch, written into the outputs that keep it
+    pub dpi: Option<u32>,
+}
+
+/// The final outcome of the pipeline, holding the encoded bytes of a rendition.
+#[derive(Debug, Clone)]
+pub struct Encoded {
+    pub exif_info: ExifInfo,
+    pub data: Vec<u8>,
+    pub rendition: Rendition,
+}
+
+/// Photos in the stages for tests.
+#[cfg(test)]
+pub mod fixture {
+    use image::{ColorType, RgbImage};
+
+    use crate::{
+        entity::{info::fixture::exif_info, Caption},
+        film::paint::layout::Layout,
+    };
+
+    use super::{Decoded, Painted};
+
+    /// The image decoded from a photo with all the EXIF fields, without a caption or profile.
+    pub fn decoded(image: RgbImage) -> Decoded {
+        Decoded {
+            exif_info: exif_info(),
+            caption: Caption::default(),
+            image,
+            color_type: ColorType::Rgb8,
+            icc_profile: None,
+            scale: 1.0,
+        }
+    }
+
+    /// The image painted from a photo with all the EXIF fields, with no regions or DPI.
+    pub fn painted(image: RgbImage, icc_profile: Option<Vec<u8>>) -> Painted {
+        Painted {
+            exif_info: exif_info(),
+            image,
+            color_type: ColorType::Rgb8,
+            icc_profile,
+            layout: Layout::new(),
+            dpi: None,
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use std::{
-    io::{BufReader, Cursor},
+    error::Error,
+    io::{Cursor, Write},
+    net::SocketAddr,
+    sync::Arc,
     time::Duration,
 };
 
@@ -8,21 +11,42 @@ use axum::{
     extract::{DefaultBodyLimit, MatchedPath, Multipart, Query, State},
     http::{header, Request, StatusCode},
     response::{IntoResponse, Response},
+    Json,
     routing::post,
     Router,
 };
-use exif::Reader;
-use image::ImageEncoder;
+use sha2::{Digest, Sha256};
 use tokio::net::TcpListener;
 use tower_http::trace::TraceLayer;
-use tracing::{debug, error, info, info_span, warn, Span};
+use tracing::{debug, error, info, info_span, Span};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
 
 use crate::{
     api::state::{build_app_state, RustantFilmAppState},
-    argument::Arguments,
-    entity::{position, DevelopParams, ExifInfo},
-    film::paint::create_painter,
-    utility::decode::get_decoder,
+    argument::ServeArguments,
+    config::Config,
+    entity::{
+        develop::{BUNDLE_MULTIPART, BUNDLE_ZIP},
+        position, Caption, DevelopParams, InspectParams,
+    },
+    film::{
+        paint::{
+            create_painter,
+            fit::Fit,
+            frame::Frame,
+            main_side,
+            style::LineStyle,
+            text::{Orientation, Typography, TRACKING_RANGE},
+        },
+        FontSet,
+    },
+    inspect::inspect,
+    pipeline::{
+        format::{DEFAULT_DPI, DPI_RANGE},
+        preview::LAYOUT_PREVIEW_LONG_SIDE,
+        rendition, ColorManagement, DebugLayout, Downscale, Encoded, FilmFormat, Format, Length,
+        OutputProfile, Pipeline, Print, PrintSize, Rendition, WindowFit,
+    },
 };
 
 async fn not_found() -> impl IntoResponse {
@@ -40,6 +64,436 @@ async fn develop(
     mut mp: Multipart,
 ) -> Response {
     info!("handling develop request");
+    if params.preview == Some(0) {
+        return (StatusCode::BAD_REQUEST, "preview must be at least 1").into_response();
+    }
+
+    let bundle = params.bundle.clone().unwrap_or(BUNDLE_ZIP.to_string());
+    if bundle != BUNDLE_ZIP && bundle != BUNDLE_MULTIPART {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("unknown bundle {}, use zip or multipart", bundle),
+        )
+            .into_response();
+    }
+
+    // read the upload file, the renditions, the line styles & the caption
+    let mut data = None;
+    let mut renditions = Vec::new();
+    let mut line_styles = Vec::new();
+    let mut caption = Caption::default();
+    while let Some(field) = mp.next_field().await.unwrap_or(None) {
+        let name = field.name().unwrap_or_default().to_string();
+        match name.as_str() {
+            "image" => match field.bytes().await {
+                Ok(d) => data = Some(d),
+                Err(err) => {
+                    error!(
+                        err_text = err.body_text(),
+                        err_status = err.status().as_u16(),
+                        "failed to accept upload file: {}",
+                        err
+                    );
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "cannt accept upload file",
+                    )
+                        .into_response();
+                }
+            },
+            "rendition" => {
+                let text = match field.text().await {
+                    Ok(t) => t,
+                    Err(err) => {
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            format!("cannot read rendition, cause: {}", err),
+                        )
+                            .into_response();
+                    }
+                };
+                match text.parse::<Rendition>() {
+                    Ok(r) => renditions.push(r),
+                    Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+                }
+            }
+            "line_style" => {
+                let text = match field.text().await {
+                    Ok(t) => t,
+                    Err(err) => {
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            format!("cannot read line style, cause: {}", err),
+                        )
+                            .into_response();
+                    }
+                };
+                match text.parse::<LineStyle>() {
+                    Ok(s) => line_styles.push(s),
+                    Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+                }
+            }
+            "title" | "location" | "signature" => {
+                let text = match field.text().await {
+                    Ok(t) => t,
+                    Err(err) => {
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            format!("cannot read {}, cause: {}", name, err),
+                        )
+                            .into_response();
+                    }
+                };
+                match name.as_str() {
+                    "title" => caption.title = Some(text),
+                    "location" => caption.location = Some(text),
+                    _ => caption.signature = Some(text),
+                }
+            }
+            _ => debug!(name = name, "skip useless field"),
+        }
+    }
+    let data = match data {
+        Some(d) => d,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "expted file upload with field name 'image'",
+            )
+                .into_response();
+        }
+    };
+    if renditions.is_empty() {
+        renditions.push(Rendition::default());
+    }
+    let filenames = renditions.iter().map(rendition_filename).collect::<Vec<_>>();
+    if let Err(err) = rendition::check_conflicts(&renditions, &filenames) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+
+    // create painter, query params take precedence over the preset
+    let preset = match state.preset(params.preset.as_deref()) {
+        Some(p) => p,
+        None => {
+            debug!(preset = ?params.preset, "unknown preset");
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("unknown preset {}", params.preset.unwrap_or_default()),
+            )
+                .into_response();
+        }
+    };
+    let painter = params.painter.clone().or(preset.layout.painter.clone());
+    let position = params.pos.clone().or(preset.layout.position.clone());
+    let position = position::from_str(position.unwrap_or("".to_string()).as_str());
+    let padding = params.pad.or(preset.layout.padding).unwrap_or(false);
+    if params.frame.is_some() && params.format.is_some() {
+        return (StatusCode::BAD_REQUEST, "frame cannot be used with format").into_response();
+    }
+    let frame = match &params.frame {
+        Some(f) => match f.parse::<Frame>() {
+            Ok(f) => f,
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        },
+        None => preset.layout.frame.unwrap_or_default(),
+    };
+    let dpi = params.dpi.or(preset.layout.dpi);
+    if dpi.is_some_and(|d| !DPI_RANGE.contains(&d)) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("dpi must be {} to {}", DPI_RANGE.start(), DPI_RANGE.end()),
+        )
+            .into_response();
+    }
+    // a frame of the request replaces the format of the preset, like a format replaces its frame
+    let preset_format = match params.frame {
+        Some(_) => None,
+        None => preset.layout.format.as_ref(),
+    };
+    let format = match params.format.as_ref().or(preset_format) {
+        Some(f) => {
+            let film = match FilmFormat::by_name(f) {
+                Ok(f) => f,
+                Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+            };
+            let fit = match params.format_fit.as_ref().or(preset.layout.format_fit.as_ref()) {
+                Some(f) => match f.parse::<WindowFit>() {
+                    Ok(f) => f,
+                    Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+                },
+                None => WindowFit::default(),
+            };
+            let side = main_side(painter.as_deref(), position);
+            Some(Format::new(film, side, fit, dpi.unwrap_or(DEFAULT_DPI)))
+        }
+        None => None,
+    };
+    // the format turns its thick border to the painter and overrides the frame
+    let frame = format.as_ref().map_or(frame, |f| f.frame());
+    let print_size = match &params.print_size {
+        Some(s) => match s.parse::<PrintSize>() {
+            Ok(s) => Some(s),
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        },
+        None => preset.layout.print_size,
+    };
+    let bleed = match &params.bleed {
+        Some(b) => match b.parse::<Length>() {
+            Ok(b) => Some(b),
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        },
+        None => preset.layout.bleed,
+    };
+    let crop_marks = params.crop_marks.or(preset.layout.crop_marks).unwrap_or(false);
+    let print = match print_size.is_some()
+        || bleed.is_some()
+        || crop_marks
+        || preset.print_profile.is_some()
+        || dpi.is_some()
+    {
+        true => Some(Print::new(
+            print_size,
+            dpi.unwrap_or(DEFAULT_DPI),
+            bleed,
+            crop_marks,
+            preset.print_profile.clone(),
+        )),
+        false => None,
+    };
+    let color_management = match params
+        .output_profile
+        .as_ref()
+        .or(preset.layout.output_profile.as_ref())
+    {
+        Some(p) => match p.parse::<OutputProfile>().and_then(ColorManagement::new) {
+            Ok(c) => Some(c),
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        },
+        None => None,
+    };
+    let tracking = params.tracking.or(preset.layout.tracking).unwrap_or(0);
+    if !TRACKING_RANGE.contains(&tracking) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "tracking must be {} to {}",
+                TRACKING_RANGE.start(),
+                TRACKING_RANGE.end()
+            ),
+        )
+            .into_response();
+    }
+    let side_text = match params.side_text.as_ref().or(preset.layout.side_text.as_ref()) {
+        Some(s) => match s.parse::<Orientation>() {
+            Ok(o) => o,
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        },
+        None => Orientation::Horizontal,
+    };
+    let fit = match params.fit.as_ref().or(preset.layout.fit.as_ref()) {
+        Some(f) => match f.parse::<Fit>() {
+            Ok(f) => f,
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        },
+        None => Fit::Shrink,
+    };
+    let typography = Typography {
+        tracking,
+        kerning: params.kerning.or(preset.layout.kerning).unwrap_or(true),
+        ligatures: params.ligatures.or(preset.layout.ligatures).unwrap_or(true),
+        side_text,
+        lines: match line_styles.is_empty() {
+            true => preset.layout.line_styles.clone().unwrap_or_default(),
+            false => line_styles,
+        },
+        fit,
+    };
+    let painter = create_painter(
+        painter,
+        FontSet::new(
+            preset.font.clone(),
+            preset.sub_font.clone(),
+            preset.caption_font.clone(),
+        ),
+        preset.logos.clone(),
+        position,
+        padding,
+        typography,
+        frame,
+    );
+    let debug_layout = params.debug_layout.unwrap_or(false);
+    let preview = match debug_layout {
+        true => Some(params.preview.unwrap_or(LAYOUT_PREVIEW_LONG_SIDE)),
+        false => params.preview,
+    };
+    let mut pipeline = Pipeline::new(Arc::from(painter));
+    if let Some(format) = format {
+        pipeline = pipeline.with_hook(Arc::new(format));
+    }
+    if let Some(color_management) = color_management {
+        pipeline = pipeline.with_hook(Arc::new(color_management));
+    }
+    if let Some(long_side) = preview {
+        pipeline = pipeline.with_hook(Arc::new(Downscale::new(long_side)));
+    }
+    if debug_layout {
+        pipeline = pipeline.with_hook(Arc::new(DebugLayout));
+    }
+    // previews are never printed, which would scale them up to the print size
+    if let Some(print) = print.filter(|_| preview.is_none()) {
+        pipeline = pipeline.with_hook(Arc::new(print));
+    }
+
+    // form fields of the caption take precedence over the preset
+    let caption = Caption {
+        title: caption.title.or(preset.layout.title.clone()),
+        location: caption.location.or(preset.layout.location.clone()),
+        signature: caption.signature.or(preset.layout.signature.clone()),
+    };
+
+    // run the develop pipeline on the blocking pool, painting once for all the renditions
+    let task = move || {
+        let loaded = pipeline.load(data, caption)?;
+        let decoded = pipeline.decode(loaded)?;
+        let painted = pipeline.paint(decoded)?;
+        pipeline.encode_renditions(painted, &renditions)
+    };
+    let mut encoded = match tokio::task::spawn_blocking(task).await {
+        Ok(Ok(e)) => e,
+        Err(err) => {
+            error!("failed to join develop task, cause: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "cannot handle upload image",
+            )
+                .into_response();
+        }
+        Ok(Err(err)) => {
+            error!(stage = %err.stage, "cannot develop upload file, cause: {}", err.cause);
+            if err.is_bad_input() {
+                return (StatusCode::BAD_REQUEST, format!("{}", err)).into_response();
+            }
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "cannot handle upload image",
+            )
+                .into_response();
+        }
+    };
+    if let Some(e) = encoded.first() {
+        info!("handling exif info: {}", e.exif_info);
+    }
+
+    let resp = match (encoded.len(), bundle.as_str()) {
+        (1, _) => single_response(encoded.remove(0)),
+        (_, BUNDLE_MULTIPART) => multipart_response(encoded),
+        _ => zip_response(encoded),
+    };
+    match resp {
+        Ok(r) => r,
+        Err(err) => {
+            error!("cannot build response, cause: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "cannot create response").into_response()
+        }
+    }
+}
+
+/// Filename of a rendition in the response.
+fn rendition_filename(rendition: &Rendition) -> String {
+    format!("image{}.{}", rendition.suffix, rendition.format.extension())
+}
+
+/// Respond with the only rendition as the body.
+fn single_response(encoded: Encoded) -> Result<Response, Box<dyn Error>> {
+    let resp = Response::builder()
+        .header(header::CONTENT_TYPE, encoded.rendition.format.content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                r#"attachment; filename="{}""#,
+                rendition_filename(&encoded.rendition)
+            ),
+        )
+        .status(StatusCode::OK)
+        .body(Body::from(encoded.data))?;
+    Ok(resp)
+}
+
+/// Respond with a zip archive holding all the renditions.
+fn zip_response(encoded: Vec<Encoded>) -> Result<Response, Box<dyn Error>> {
+    // images are compressed already, so store them as is
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    for e in encoded.iter() {
+        archive.start_file(rendition_filename(&e.rendition), options)?;
+        archive.write_all(&e.data)?;
+    }
+    let data = archive.finish()?.into_inner();
+
+    let resp = Response::builder()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            r#"attachment; filename="images.zip""#,
+        )
+        .status(StatusCode::OK)
+        .body(Body::from(data))?;
+    Ok(resp)
+}
+
+/// Respond with a multipart/mixed body, one part per rendition.
+fn multipart_response(encoded: Vec<Encoded>) -> Result<Response, Box<dyn Error>> {
+    // derive the boundary from the contents, so it's hardly found in any of them
+    let mut hasher = Sha256::new();
+    for e in encoded.iter() {
+        hasher.update(&e.data);
+    }
+    let boundary = format!("rustant-film-{:x}", hasher.finalize());
+
+    let mut data = Vec::new();
+    for e in encoded.iter() {
+        write!(
+            data,
+            "--{}\r\nContent-Type: {}\r\nContent-Disposition: attachment; filename=\"{}\"\r\n\r\n",
+            boundary,
+            e.rendition.format.content_type(),
+            rendition_filename(&e.rendition)
+        )?;
+        data.extend_from_slice(&e.data);
+        data.extend_from_slice(b"\r\n");
+    }
+    write!(data, "--{}--\r\n", boundary)?;
+
+    let resp = Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/mixed; boundary={}", boundary),
+        )
+        .status(StatusCode::OK)
+        .body(Body::from(data))?;
+    Ok(resp)
+}
+
+#[tracing::instrument(skip(state, mp))]
+#[axum::debug_handler]
+async fn inspect_exif(
+    State(state): State<RustantFilmAppState>,
+    Query(params): Query<InspectParams>,
+    mut mp: Multipart,
+) -> Response {
+    info!("handling inspect request");
+
+    let preset = match state.preset(params.preset.as_deref()) {
+        Some(p) => p,
+        None => {
+            debug!(preset = ?params.preset, "unknown preset");
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("unknown preset {}", params.preset.unwrap_or_default()),
+            )
+                .into_response();
+        }
+    };
 
     while let Some(field) = mp.next_field().await.unwrap_or(None) {
         let name = field.name().unwrap_or_default().to_string();
@@ -47,6 +501,7 @@ async fn develop(
             debug!(name = name, "skip useless field");
             continue;
         }
+        let filename = field.file_name().unwrap_or("image").to_string();
 
         // read upload file into memory
         let data = match field.bytes().await {
@@ -66,131 +521,17 @@ async fn develop(
             }
         };
 
-        // create painter
-        let painter = params.painter.clone();
-        let position = position::from_str(params.pos.clone().unwrap_or("".to_string()).as_str());
-        let padding = params.pad.unwrap_or(false);
-        let painter = create_painter(
-            painter,
-            state.font.clone(),
-            state.sub_font.clone(),
-            state.logos.clone(),
-            position,
-            padding,
-        );
-
-        // load exif info
-        let cursor = Cursor::new(&data);
-        let mut reader = BufReader::new(cursor);
-        let exif = match Reader::new().read_from_container(&mut reader) {
-            Ok(exif) => exif,
+        return match inspect(&filename, &data, &preset.logos) {
+            Ok(report) => Json(report).into_response(),
             Err(err) => {
-                error!("cannot parse EXIF, cause: {}", err);
-                return (StatusCode::BAD_REQUEST, "cannot parse EXIF from file").into_response();
-            }
-        };
-        let exif_info = ExifInfo::new(&exif);
-        debug!(exif_info = ?exif_info, "get exif info from image");
-
-        // load exif info
-        let exif = match Reader::new().read_from_container(&mut reader) {
-            Ok(exif) => exif,
-            Err(e) => {
-                error!("cannot read EXIF cause: {}", e);
-                continue;
-            }
-        };
-        let exif_info = ExifInfo::new(&exif);
-        info!("handling exif info: {}", exif_info);
-
-        // create decoder to read image
-        let mut decoder = match get_decoder(data) {
-            Ok(d) => d,
-            Err(e) => {
-                warn!("cannot get decoder cause: {}", e);
-                continue;
-            }
-        };
-
-        // get the potential ICC
-        let icc_profile = match decoder.icc_profile() {
-            Ok(p) => p,
-            Err(e) => {
-                warn!("cannot get ICC profile cause: {}", e);
-                continue;
-            }
-        };
-        if icc_profile.is_none() {
-            debug!("no embedding ICC profile");
-        }
-
-        // decode the image into RgbImage
-        let (width, height) = decoder.dimensions();
-        let color_type = decoder.color_type();
-        if color_type != image::ColorType::Rgb8 {
-            warn!("cannot handle color type {:?}, skipping...", color_type);
-            continue;
-        }
-        let total_bytes = decoder.total_bytes() as usize;
-        let mut buffer = vec![0u8; total_bytes];
-        if let Err(e) = decoder.read_image_boxed(&mut buffer) {
-            warn!("cannot read image, cause: {}", e);
-            continue;
-        }
-        let mut image = match image::RgbImage::from_raw(width, height, buffer) {
-            Some(img) => img,
-            None => {
-                warn!("cannot decode image");
-                continue;
-            }
-        };
-
-        // draw the image
-        if let Err(err) = painter.paint(&mut image, &exif_info) {
-            error!("cannot paint image cause: {}", err);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "cannt paint new image").into_response();
-        }
-
-        // create jpeg encoder
-        let mut buffer = Vec::new();
-        let mut encoder = image::codecs::jpeg::JpegEncoder::new(&mut buffer);
-        if let Some(profile) = icc_profile {
-            if let Err(e) = encoder.set_icc_profile(profile) {
-                warn!("cannot set ICC profile to output file which may lead to incorrect color, cause: {}", e);
-            }
-        }
-
-        if let Err(err) = encoder.encode(
-            &image.as_raw(),
-            image.width(),
-            image.height(),
-            color_type.into(),
-        ) {
-            error!("cannot convert new image to bytes, cause: {}", err);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "cannot handle upload image",
-            )
-                .into_response();
-        }
-
-        let resp = Response::builder()
-            .header(header::CONTENT_TYPE, "image/jpeg")
-            .header(
-                header::CONTENT_DISPOSITION,
-                r#"attachment; filename="image.jpeg""#,
-            )
-            .status(StatusCode::OK)
-            .body(Body::from(buffer));
-        let resp = match resp {
-            Ok(r) => r,
-            Err(err) => {
-                error!("cannot build response, cause: {}", err);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "cannot create response")
-                    .into_response();
+                error!("cannot read exif from upload file, cause: {}", err);
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("cannot read EXIF, cause: {}", err),
+                )
+                    .into_response()
             }
         };
-        return resp;
     }
 
     (
@@ -200,13 +541,15 @@ async fn develop(
         .into_response()
 }
 
-pub async fn run(args: Arguments) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn run(args: ServeArguments, config: Config) -> Result<(), Box<dyn std::error::Error>> {
     // setup app state
-    let state = build_app_state(args.logos, args.font, args.sub_font)?;
+    let state = build_app_state(&args.resources, &config, args.presets.preset.as_deref())?;
+    info!(presets = ?state.presets.keys().collect::<Vec<_>>(), "presets loaded");
 
     // build app
     let app = Router::new()
         .route("/api/v1/develop", post(develop))
+        .route("/api/v1/inspect", post(inspect_exif))
         .layer(DefaultBodyLimit::max(1024 * 1024 * 200))    // 200MB upload image limit
         .layer(
             TraceLayer::new_for_http()
@@ -231,8 +574,8 @@ pub async fn run(args: Arguments) -> Result<(), Box<dyn std::error::Error>> {
         .with_state(state);
 
     // listen
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", args.port)).await?;
-    debug!(port = args.port, "listening port...");
+    let listener = TcpListener::bind(SocketAddr::new(args.bind, args.port)).await?;
+    debug!(bind = %args.bind, port = args.port, "listening port...");
     axum::serve(listener, app).await?;
 
     Ok(())
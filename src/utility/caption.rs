@@ -0,0 +1,75 @@
+use std::{
+    error::Error,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::entity::Caption;
+
+/// Suffix of the caption sidecar of a photo, e.g. `photo.caption.toml` for `photo.jpg`.
+pub const SIDECAR_SUFFIX: &str = ".caption.toml";
+
+/// Path to the caption sidecar of the photo, next to it.
+///
+/// The sidecar is named by the stem of the photo alone, so photos named alike, like `IMG.jpg`
+/// & `IMG.png`, share it.
+pub fn sidecar_path(photo: &Path) -> PathBuf {
+    let stem = photo
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    photo.with_file_name(format!("{}{}", stem, SIDECAR_SUFFIX))
+}
+
+/// Whether the file is a caption sidecar rather than a photo.
+pub fn is_sidecar(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_ascii_lowercase().ends_with(SIDECAR_SUFFIX))
+        .unwrap_or(false)
+}
+
+/// Stem of the photos the caption sidecar belongs to, e.g. `photo` for `photo.caption.toml`.
+pub fn sidecar_stem(sidecar: &Path) -> Option<String> {
+    let name = sidecar.file_name()?.to_string_lossy().to_string();
+    match is_sidecar(sidecar) {
+        true => Some(name[..name.len() - SIDECAR_SUFFIX.len()].to_string()),
+        false => None,
+    }
+}
+
+/// Read the caption sidecar of the photo if there is one, like:
+///
+/// ```toml
+/// title = "Morning Tide"
+/// location = "Kamakura"
+/// signature = "Zhu"
+/// ```
+///
+/// The content is returned as well, which tells whether the sidecar changes.
+pub fn read_sidecar(photo: &Path) -> Result<Option<(Caption, String)>, Box<dyn Error>> {
+    let path = sidecar_path(photo);
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("cannot read caption {}, cause: {}", path.display(), e).into()),
+    };
+    match toml::from_str(&content) {
+        Ok(caption) => Ok(Some((caption, content))),
+        Err(e) => Err(format!("invalid caption {}, cause: {}", path.display(), e).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecar_belongs_to_photos_of_its_stem() {
+        let sidecar = sidecar_path(Path::new("in/IMG_0001.jpg"));
+        assert_eq!(sidecar, Path::new("in/IMG_0001.caption.toml"));
+        assert_eq!(sidecar_stem(&sidecar).as_deref(), Some("IMG_0001"));
+        assert_eq!(sidecar_stem(Path::new("in/IMG.Caption.TOML")).as_deref(), Some("IMG"));
+        assert_eq!(sidecar_stem(Path::new("in/IMG_0001.jpg")), None);
+    }
+}
@@ -1,15 +1,290 @@
-use std::{fs::File, io::Read};
+use std::{error::Error, fmt::Display, fs::File, io::Read};
 
-pub fn read_font_data(filename: &str) -> Result<ab_glyph::FontVec, Box<dyn std::error::Error>> {
+use ab_glyph::{FontVec, VariableFont};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use ttf_parser::{name_id, Face, Tag};
+
+/// Tag of a variation axis & the value to set.
+pub type AxisValue = ([u8; 4], f32);
+
+/// A face loaded from a font file, with its index & variation to shape text with.
+#[derive(Debug)]
+pub struct Typeface {
+    pub font: FontVec,
+    pub index: u32,
+    pub variation: Vec<AxisValue>,
+}
+
+/// Which face of a font file to load, and where to set its variation axes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FontOptions {
+    /// face of a collection like `.ttc`, by index or by name, the first face if not set
+    pub face: Option<String>,
+    /// named instance of a variable font like `Bold`, or axis values like `wght=700,wdth=90`
+    pub variation: Option<String>,
+}
+
+impl Display for FontOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "face: {}, variation: {}",
+            self.face.as_deref().unwrap_or("(None)"),
+            self.variation.as_deref().unwrap_or("(None)"),
+        )
+    }
+}
+
+pub fn read_font_data(filename: &str, options: &FontOptions) -> Result<Typeface, Box<dyn Error>> {
     let mut file = File::open(filename)?;
     let mut font_data = Vec::new();
     file.read_to_end(&mut font_data)?;
-    Ok(ab_glyph::FontVec::try_from_vec(font_data)?)
+
+    let index = match &options.face {
+        Some(face) => find_face(&font_data, face)?,
+        None => 0,
+    };
+    let variation = match &options.variation {
+        Some(variation) => parse_variation(&font_data, index, variation)?,
+        None => Vec::new(),
+    };
+
+    let mut font = FontVec::try_from_vec_and_index(font_data, index)?;
+    for (tag, value) in variation.iter() {
+        font.set_variation(tag, *value);
+    }
+    Ok(Typeface {
+        font,
+        index,
+        variation,
+    })
 }
 
-pub fn read_sub_font_data(filename: &str) -> Option<ab_glyph::FontVec> {
-    let mut file = File::open(filename).ok()?;
-    let mut font_data = Vec::new();
-    file.read_to_end(&mut font_data).ok()?;
-    Some(ab_glyph::FontVec::try_from_vec(font_data).ok()?)
+pub fn read_sub_font_data(filename: &str, options: &FontOptions) -> Option<Typeface> {
+    read_optional_font_data("sub font", filename, options)
+}
+
+pub fn read_caption_font_data(filename: &str, options: &FontOptions) -> Option<Typeface> {
+    read_optional_font_data("caption font", filename, options)
+}
+
+/// Load a font the painters can do without, warning if it cannot be loaded.
+fn read_optional_font_data(kind: &str, filename: &str, options: &FontOptions) -> Option<Typeface> {
+    match read_font_data(filename, options) {
+        Ok(f) => Some(f),
+        Err(e) => {
+            warn!("cannot load {} from file: {}, cause: {}", kind, filename, e);
+            None
+        }
+    }
+}
+
+pub fn read_fallback_fonts(filenames: &[String]) -> Result<Vec<Typeface>, Box<dyn Error>> {
+    let mut fonts = Vec::with_capacity(filenames.len());
+    for filename in filenames {
+        match read_font_data(filename, &FontOptions::default()) {
+            Ok(f) => fonts.push(f),
+            Err(e) => {
+                return Err(format!("cannot load fallback font {}, cause: {}", filename, e).into())
+            }
+        }
+    }
+    Ok(fonts)
+}
+
+/// Get the name of the face, like `Noto Sans CJK SC Bold`.
+fn face_name(face: &Face) -> String {
+    get_name(face, name_id::FULL_NAME)
+        .or_else(|| get_name(face, name_id::POST_SCRIPT_NAME))
+        .or_else(|| get_name(face, name_id::FAMILY))
+        .unwrap_or_default()
+}
+
+fn get_name(face: &Face, id: u16) -> Option<String> {
+    face.names()
+        .into_iter()
+        .filter(|n| n.name_id == id)
+        .find_map(|n| n.to_string())
+}
+
+/// Find the index of the face in a font file, by index or by its full, PostScript or family name.
+fn find_face(data: &[u8], face: &str) -> Result<u32, Box<dyn Error>> {
+    let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+    if let Ok(index) = face.parse::<u32>() {
+        if index >= count {
+            return Err(format!(
+                "face index {} out of range, the font has {} faces",
+                index, count
+            )
+            .into());
+        }
+        return Ok(index);
+    }
+
+    let mut names = Vec::new();
+    for index in 0..count {
+        let parsed = match Face::parse(data, index) {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+        let matched = [
+            name_id::FULL_NAME,
+            name_id::POST_SCRIPT_NAME,
+            name_id::FAMILY,
+        ]
+        .into_iter()
+        .filter_map(|id| get_name(&parsed, id))
+        .any(|name| name.eq_ignore_ascii_case(face));
+        if matched {
+            return Ok(index);
+        }
+        names.push(format!("{}: {}", index, face_name(&parsed)));
+    }
+    Err(format!(
+        "no face named `{}`, available faces: [{}]",
+        face,
+        names.join(", ")
+    )
+    .into())
+}
+
+/// Get the axis values to set on a variable font, from a named instance or `tag=value` pairs.
+fn parse_variation(
+    data: &[u8],
+    index: u32,
+    variation: &str,
+) -> Result<Vec<AxisValue>, Box<dyn Error>> {
+    let face = Face::parse(data, index)?;
+    let axes = match face.tables().fvar {
+        Some(fvar) => fvar.axes,
+        None => return Err(format!("font `{}` is not a variable font", face_name(&face)).into()),
+    };
+
+    // named instance
+    if !variation.contains('=') {
+        let instances = named_instances(&face);
+        return match instances
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(variation.trim()))
+        {
+            Some((_, values)) => Ok(values.clone()),
+            None => Err(format!(
+                "no instance named `{}`, available instances: [{}]",
+                variation,
+                instances
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into()),
+        };
+    }
+
+    // axis values
+    let mut ret = Vec::new();
+    for pair in variation
+        .split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+    {
+        let (key, value) = match pair.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(format!("expect `axis=value` in variation, got `{}`", pair).into()),
+        };
+        let tag = match key.to_ascii_lowercase().as_str() {
+            "weight" => *b"wght",
+            "width" => *b"wdth",
+            "slant" => *b"slnt",
+            "italic" => *b"ital",
+            "optical_size" => *b"opsz",
+            _ if key.len() == 4 && key.is_ascii() => {
+                let mut tag = [0u8; 4];
+                tag.copy_from_slice(key.as_bytes());
+                tag
+            }
+            _ => return Err(format!("unknown variation axis `{}`", key).into()),
+        };
+        let value = match value.parse::<f32>() {
+            Ok(v) => v,
+            Err(_) => {
+                return Err(format!("invalid value of variation axis `{}`: {}", key, value).into())
+            }
+        };
+        let axis = match axes.into_iter().find(|a| a.tag == Tag::from_bytes(&tag)) {
+            Some(a) => a,
+            None => {
+                return Err(format!(
+                    "font has no variation axis `{}`, available axes: [{}]",
+                    key,
+                    axes.into_iter()
+                        .map(|a| format!("{} {}..{}", a.tag, a.min_value, a.max_value))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                .into())
+            }
+        };
+        if value < axis.min_value || value > axis.max_value {
+            return Err(format!(
+                "value of variation axis `{}` must be {} to {}, got {}",
+                key, axis.min_value, axis.max_value, value
+            )
+            .into());
+        }
+        ret.push((tag, value));
+    }
+    Ok(ret)
+}
+
+/// Named instances of a variable font, with their axis values, read from the `fvar` table.
+fn named_instances(face: &Face) -> Vec<(String, Vec<AxisValue>)> {
+    let read_u16 = |data: &[u8], offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+    };
+    let read_fixed = |data: &[u8], offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f32 / 65536.0)
+    };
+
+    let mut ret = Vec::new();
+    let data = match face.raw_face().table(Tag::from_bytes(b"fvar")) {
+        Some(d) => d,
+        None => return ret,
+    };
+    let (axes_offset, axis_count, axis_size, instance_count, instance_size) = match (
+        read_u16(data, 4),
+        read_u16(data, 8),
+        read_u16(data, 10),
+        read_u16(data, 12),
+        read_u16(data, 14),
+    ) {
+        (Some(a), Some(b), Some(c), Some(d), Some(e)) => (a, b, c, d, e),
+        _ => return ret,
+    };
+
+    // instances are stored right after the axes, each starts with its name id & flags
+    let tags: Vec<[u8; 4]> = (0..axis_count)
+        .filter_map(|i| data.get(axes_offset + i * axis_size..axes_offset + i * axis_size + 4))
+        .map(|b| [b[0], b[1], b[2], b[3]])
+        .collect();
+    let instances_offset = axes_offset + axis_count * axis_size;
+    for i in 0..instance_count {
+        let offset = instances_offset + i * instance_size;
+        let name = match read_u16(data, offset).and_then(|id| get_name(face, id as u16)) {
+            Some(n) => n,
+            None => continue,
+        };
+        let values: Option<Vec<AxisValue>> = tags
+            .iter()
+            .enumerate()
+            .map(|(a, tag)| read_fixed(data, offset + 4 + a * 4).map(|v| (*tag, v)))
+            .collect();
+        if let Some(values) = values {
+            ret.push((name, values));
+        }
+    }
+    ret
 }
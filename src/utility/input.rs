@@ -0,0 +1,206 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::utility::{caption::is_sidecar, manifest::MANIFEST_FILENAME};
+
+/// An input file to develop.
+#[derive(Debug, Clone)]
+pub struct InputFile {
+    /// path to the file
+    pub path: PathBuf,
+    /// path relative to the input directory, used to mirror the input tree in output
+    pub relative: PathBuf,
+}
+
+/// Decides which files under the input directory get developed.
+///
+/// A file is picked if its relative path matches any `include` pattern (or there is none),
+/// matches no `exclude` pattern, and has one of the `extensions` (or there is none). Caption
+/// sidecars & manifests are never picked.
+#[derive(Debug, Clone)]
+pub struct InputFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    extensions: Vec<String>,
+}
+
+impl InputFilter {
+    pub fn new(
+        include: &[String],
+        exclude: &[String],
+        extensions: &[String],
+    ) -> Result<Self, Box<dyn Error>> {
+        let extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+
+        Ok(InputFilter {
+            include: build_glob_set(include)?,
+            exclude: build_glob_set(exclude)?,
+            extensions,
+        })
+    }
+
+    pub fn is_match(&self, relative: &Path) -> bool {
+        if is_sidecar(relative) || relative.file_name() == Some(MANIFEST_FILENAME.as_ref()) {
+            return false;
+        }
+        if let Some(include) = &self.include {
+            if !include.is_match(relative) {
+                return false;
+            }
+        }
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(relative) {
+                return false;
+            }
+        }
+        if self.extensions.is_empty() {
+            return true;
+        }
+
+        let ext = relative
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        self.extensions.contains(&ext)
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, Box<dyn Error>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    // `*` stays in one directory and `**` crosses them, as patterns are relative to the input
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// List the files under `root` that pass the filter, sorted by path.
+///
+/// Only the top level of `root` is listed unless `recursive` is set. The `output` directory is
+/// skipped if it's inside `root`, so the outputs of earlier runs are never developed again.
+pub fn list_input_files(
+    root: &str,
+    recursive: bool,
+    filter: &InputFilter,
+    output: &str,
+) -> Result<Vec<InputFile>, Box<dyn Error>> {
+    let root_path = Path::new(root);
+    if !root_path.is_dir() {
+        return Err(format!("{} is not a directory", root).into());
+    }
+    // the output relative to the input, found by their real paths as they may be written apart
+    let output_relative = match (root_path.canonicalize(), Path::new(output).canonicalize()) {
+        (Ok(root), Ok(output)) => output.strip_prefix(root).ok().map(Path::to_path_buf),
+        _ => None,
+    };
+
+    let max_depth = if recursive { usize::MAX } else { 1 };
+    let mut files = Vec::new();
+    let entries = WalkDir::new(root_path)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| {
+            let inside_output = output_relative.as_deref().is_some_and(|output| {
+                entry.path().strip_prefix(root_path).is_ok_and(|r| r.starts_with(output))
+            });
+            if inside_output {
+                debug!("skip output {}", entry.path().display());
+            }
+            !inside_output
+        });
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("cannot get file, cause: {}, skipping...", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let path = entry.into_path();
+        let relative = match path.strip_prefix(root_path) {
+            Ok(r) => r.to_path_buf(),
+            Err(_) => continue,
+        };
+        if !filter.is_match(&relative) {
+            debug!("skip filtered file {}", path.display());
+            continue;
+        }
+        files.push(InputFile { path, relative });
+    }
+    files.sort_by(|a, b| a.relative.cmp(&b.relative));
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> InputFilter {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        InputFilter::new(&strings(include), &strings(exclude), &[]).unwrap()
+    }
+
+    #[test]
+    fn star_stays_in_one_directory() {
+        let f = filter(&["*.jpg"], &[]);
+        assert!(f.is_match(Path::new("a.jpg")));
+        assert!(!f.is_match(Path::new("2026/a.jpg")));
+    }
+
+    #[test]
+    fn double_star_crosses_directories() {
+        let f = filter(&["2026/**/*.jpg"], &["**/raw/**"]);
+        assert!(f.is_match(Path::new("2026/05/a.jpg")));
+        assert!(!f.is_match(Path::new("2026/raw/a.jpg")));
+        assert!(!f.is_match(Path::new("2025/a.jpg")));
+    }
+
+    #[test]
+    fn sidecars_are_never_picked() {
+        let f = filter(&[], &[]);
+        assert!(!f.is_match(Path::new("a.caption.toml")));
+    }
+
+    #[test]
+    fn output_inside_input_is_skipped() {
+        let root = std::env::temp_dir().join(format!("rustant-film-input-{}", std::process::id()));
+        let output = root.join("framed");
+        std::fs::create_dir_all(output.join("2026")).unwrap();
+        for file in ["a.jpg", "framed/a.jpg", "framed/2026/b.jpg", "framed/.rustant-film-manifest.json"] {
+            std::fs::write(root.join(file), b"").unwrap();
+        }
+        std::fs::write(root.join(MANIFEST_FILENAME), b"").unwrap();
+
+        let (input, output) = (root.to_str().unwrap(), output.to_str().unwrap());
+        let files = list_input_files(input, true, &filter(&[], &[]), output).unwrap();
+        let relative = files.iter().map(|f| f.relative.clone()).collect::<Vec<_>>();
+        // given apart, like `-i in -o ./in/framed`
+        let apart = format!("{}/../{}/framed", input, root.file_name().unwrap().to_str().unwrap());
+        let files_apart = list_input_files(input, true, &filter(&[], &[]), &apart).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(relative, vec![PathBuf::from("a.jpg")]);
+        assert_eq!(files_apart.len(), 1);
+    }
+}
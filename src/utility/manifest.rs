@@ -0,0 +1,102 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Filename of the manifest, stored in the output directory.
+pub const MANIFEST_FILENAME: &str = ".rustant-film-manifest.json";
+
+const MANIFEST_VERSION: u32 = 2;
+
+/// What a previous run developed from an input file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub input_hash: String,
+    pub options_hash: String,
+    /// files written, one for each rendition
+    pub outputs: Vec<String>,
+}
+
+/// Records of developed files, letting later runs skip the unchanged ones.
+///
+/// Entries are keyed by the input path relative to the input directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    version: u32,
+    entries: BTreeMap<String, ManifestEntry>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Manifest {
+            version: MANIFEST_VERSION,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn path_in(output_directory: &str) -> PathBuf {
+        Path::new(output_directory).join(MANIFEST_FILENAME)
+    }
+
+    /// Load the manifest, an empty one is returned if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Manifest::new());
+        }
+        let data = fs::read(path)?;
+        let manifest: Manifest = serde_json::from_slice(&data)?;
+        if manifest.version != MANIFEST_VERSION {
+            return Ok(Manifest::new());
+        }
+        Ok(manifest)
+    }
+
+    /// Save the manifest, written to a temporary file first so it's never left half-written.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Get the outputs developed last time, if the input & options are unchanged and the outputs
+    /// are still there.
+    pub fn get_up_to_date(
+        &self,
+        relative: &str,
+        input_hash: &str,
+        options_hash: &str,
+    ) -> Option<&ManifestEntry> {
+        let entry = self.entries.get(relative)?;
+        if entry.input_hash != input_hash || entry.options_hash != options_hash {
+            return None;
+        }
+        if entry.outputs.is_empty() || !entry.outputs.iter().all(|o| Path::new(o).exists()) {
+            return None;
+        }
+        Some(entry)
+    }
+
+    pub fn insert(&mut self, relative: String, entry: ManifestEntry) {
+        self.entries.insert(relative, entry);
+    }
+}
+
+/// Hex encoded SHA-256 of the data.
+pub fn digest(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
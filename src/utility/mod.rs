@@ -1,2 +1,7 @@
+pub mod caption;
 pub mod font;
-pub mod decode;
\ No newline at end of file
+pub mod decode;
+pub mod input;
+pub mod manifest;
+pub mod output;
+pub mod report;
\ No newline at end of file
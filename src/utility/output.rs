@@ -0,0 +1,406 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use log::warn;
+
+use crate::entity::ExifInfo;
+
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{stem}.{ext}";
+
+/// Fields of the template taken from the photo itself rather than the EXIF info.
+const FILE_KEYS: [&str; 3] = ["stem", "ext", "painter"];
+
+const TEMPLATE_KEYS: [&str; 12] = [
+    "stem",
+    "ext",
+    "painter",
+    "artist",
+    "lens",
+    "maker",
+    "model",
+    "aperture",
+    "focal_length",
+    "exposure",
+    "iso",
+    "date",
+];
+
+/// What to do if the output file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// replace the existing file
+    Overwrite,
+    /// leave the existing file alone and skip the input
+    SkipExisting,
+    /// append `_1`, `_2`, ... to the file stem until it's free
+    SuffixOnConflict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field { key: String, format: Option<String> },
+}
+
+/// Template of output filenames, like `{stem}_{painter}_{date:%Y%m%d}.{ext}`.
+///
+/// Available fields are `stem`, `ext`, `painter` and the EXIF fields `artist`, `lens`, `maker`,
+/// `model`, `aperture`, `focal_length`, `exposure`, `iso` and `date`. The `date` field takes an
+/// optional format using `%Y`, `%y`, `%m`, `%d`, `%H`, `%M` and `%S`. Missing EXIF fields render
+/// as `unknown`. Use `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate {
+    segments: Vec<Segment>,
+}
+
+impl FilenameTemplate {
+    pub fn new(template: &str) -> Result<Self, Box<dyn Error>> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut field = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        field.push(c);
+                    }
+                    if !closed {
+                        return Err(format!("unclosed field in template {}", template).into());
+                    }
+
+                    let (key, format) = match field.split_once(':') {
+                        Some((key, format)) => (key.trim(), Some(format.to_string())),
+                        None => (field.trim(), None),
+                    };
+                    if !TEMPLATE_KEYS.contains(&key) {
+                        return Err(format!(
+                            "unknown field `{}` in template, use one of {}",
+                            key,
+                            TEMPLATE_KEYS.join(", ")
+                        )
+                        .into());
+                    }
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field {
+                        key: key.to_string(),
+                        format,
+                    });
+                }
+                '}' => return Err(format!("unmatched `}}` in template {}", template).into()),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        if segments.is_empty() {
+            return Err("filename template is empty".into());
+        }
+
+        Ok(FilenameTemplate { segments })
+    }
+
+    /// Whether the filename depends on the EXIF info, so it's only known once the photo is read.
+    pub fn uses_exif(&self) -> bool {
+        self.segments.iter().any(|s| match s {
+            Segment::Field { key, .. } => !FILE_KEYS.contains(&key.as_str()),
+            Segment::Literal(_) => false,
+        })
+    }
+
+    /// Render the filename, field values are sanitized so they never introduce directories.
+    pub fn render(&self, stem: &str, ext: &str, painter: &str, exif_info: &ExifInfo) -> String {
+        let mut ret = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(s) => ret.push_str(s),
+                Segment::Field { key, format } => {
+                    let value = match key.as_str() {
+                        "stem" => Some(stem.to_string()),
+                        "ext" => Some(ext.to_string()),
+                        "painter" => Some(painter.to_string()),
+                        "artist" => exif_info.artist.clone(),
+                        "lens" => exif_info.lens_model.clone(),
+                        "maker" => exif_info.camera_maker.clone(),
+                        "model" => exif_info.camera_model.clone(),
+                        "aperture" => exif_info.aperture.clone(),
+                        "focal_length" => exif_info.focal_length.clone(),
+                        "exposure" => exif_info.exposure_time.clone(),
+                        "iso" => exif_info.iso.clone(),
+                        "date" => exif_info
+                            .datetime
+                            .as_deref()
+                            .and_then(|d| format_datetime(d, format.as_deref().unwrap_or("%Y%m%d"))),
+                        _ => None,
+                    };
+                    let value = value
+                        .map(|v| v.trim().to_string())
+                        .filter(|v| !v.is_empty())
+                        .unwrap_or_else(|| "unknown".to_string());
+                    ret.push_str(&sanitize(&value));
+                }
+            }
+        }
+        ret
+    }
+}
+
+/// Format an EXIF datetime like `2026-10-18 09:30:00` with a strftime-like format.
+fn format_datetime(datetime: &str, format: &str) -> Option<String> {
+    let parts = datetime
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>();
+    if parts.len() < 3 {
+        return None;
+    }
+    let part = |i: usize| parts.get(i).copied().unwrap_or("00");
+
+    let mut ret = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => ret.push_str(part(0)),
+            Some('y') => ret.push_str(&part(0)[part(0).len().saturating_sub(2)..]),
+            Some('m') => ret.push_str(part(1)),
+            Some('d') => ret.push_str(part(2)),
+            Some('H') => ret.push_str(part(3)),
+            Some('M') => ret.push_str(part(4)),
+            Some('S') => ret.push_str(part(5)),
+            Some('%') => ret.push('%'),
+            Some(other) => {
+                ret.push('%');
+                ret.push(other);
+            }
+            None => ret.push('%'),
+        }
+    }
+    Some(ret)
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Output paths claimed by the inputs of a run, so two inputs never write the same file.
+///
+/// Inputs mapped to the same paths, like `IMG_0001.jpg` & `IMG_0001.png` by `{stem}.{ext}`, are
+/// told apart by appending the extension of the input to the stem of the later one, e.g.
+/// `IMG_0001_png.jpg`.
+#[derive(Debug, Default)]
+pub struct OutputClaims {
+    /// input path of each claimed output path
+    claims: Mutex<HashMap<PathBuf, PathBuf>>,
+}
+
+impl OutputClaims {
+    /// Claim the output paths for the input, returns the paths it should write to.
+    pub fn claim(&self, input: &Path, paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
+        let mut claims = self
+            .claims
+            .lock()
+            .map_err(|e| format!("cannot lock output claims, cause: {}", e))?;
+        let taken_by = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .find_map(|p| claims.get(p).filter(|owner| owner.as_path() != input).cloned())
+        };
+
+        let paths = match taken_by(&paths) {
+            None => paths,
+            Some(owner) => {
+                let ext = input
+                    .extension()
+                    .map(|e| format!("_{}", e.to_string_lossy()))
+                    .unwrap_or_else(|| "_".to_string());
+                let renamed = paths
+                    .iter()
+                    .map(|p| {
+                        let filename = p.file_name().unwrap_or_default().to_string_lossy();
+                        p.with_file_name(append_to_stem(&filename, &ext))
+                    })
+                    .collect::<Vec<_>>();
+                if let Some(other) = taken_by(&renamed) {
+                    return Err(format!(
+                        "{} is mapped to the outputs of both {} & {}, use a `--name` template that tells them apart",
+                        input.display(),
+                        owner.display(),
+                        other.display()
+                    ));
+                }
+                // warn only once, the outputs may be claimed before developing
+                if renamed.iter().any(|p| !claims.contains_key(p)) {
+                    warn!(
+                        "{} is mapped to the outputs of {}, writing to {} instead",
+                        input.display(),
+                        owner.display(),
+                        renamed
+                            .iter()
+                            .map(|p| p.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                renamed
+            }
+        };
+        for path in &paths {
+            claims.insert(path.clone(), input.to_path_buf());
+        }
+        Ok(paths)
+    }
+}
+
+/// Append the suffix to the stem of the filename, e.g. `photo.jpg` to `photo_2048.jpg`.
+pub fn append_to_stem(filename: &str, suffix: &str) -> String {
+    match filename.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}{}.{}", stem, suffix, ext),
+        None => format!("{}{}", filename, suffix),
+    }
+}
+
+/// Write the data to the path following the policy.
+///
+/// Returns the path actually written, or `None` if skipped since the file exists.
+pub fn write_output(
+    path: &Path,
+    data: &[u8],
+    policy: OverwritePolicy,
+) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match policy {
+        OverwritePolicy::Overwrite => {
+            fs::write(path, data)?;
+            Ok(Some(path.to_path_buf()))
+        }
+        OverwritePolicy::SkipExisting => match create_new(path, data) {
+            Ok(()) => Ok(Some(path.to_path_buf())),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e.into()),
+        },
+        OverwritePolicy::SuffixOnConflict => {
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let ext = path.extension().map(|e| e.to_string_lossy().to_string());
+            let mut candidate = path.to_path_buf();
+            let mut index: u32 = 0;
+            loop {
+                match create_new(&candidate, data) {
+                    Ok(()) => return Ok(Some(candidate)),
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                        index += 1;
+                        let filename = match &ext {
+                            Some(ext) => format!("{}_{}.{}", stem, index, ext),
+                            None => format!("{}_{}", stem, index),
+                        };
+                        candidate = path.with_file_name(filename);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+/// Create the file only if it does not exist, which is atomic among parallel jobs.
+fn create_new(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exif_info() -> ExifInfo {
+        let mut exif_info = ExifInfo::new_none();
+        exif_info.camera_model = Some("X100/VI".to_string());
+        exif_info.datetime = Some("2026:10:18 09:30:05".to_string());
+        exif_info
+    }
+
+    #[test]
+    fn render_default_template() {
+        let template = FilenameTemplate::new(DEFAULT_FILENAME_TEMPLATE).unwrap();
+        let filename = template.render("IMG_0001", "jpg", "triangular", &exif_info());
+        assert_eq!(filename, "IMG_0001.jpg");
+        assert!(!template.uses_exif());
+    }
+
+    #[test]
+    fn render_exif_fields() {
+        let template = FilenameTemplate::new("{model}_{date:%y-%m-%d_%H%M%S}_{iso}.{ext}").unwrap();
+        let filename = template.render("IMG_0001", "png", "triangular", &exif_info());
+        // slashes in values never introduce directories, missing values are `unknown`
+        assert_eq!(filename, "X100_VI_26-10-18_093005_unknown.png");
+        assert!(template.uses_exif());
+    }
+
+    #[test]
+    fn render_literal_braces() {
+        let template = FilenameTemplate::new("{{{stem}}}.{ext}").unwrap();
+        let filename = template.render("a", "jpg", "blank", &ExifInfo::new_none());
+        assert_eq!(filename, "{a}.jpg");
+    }
+
+    #[test]
+    fn reject_invalid_templates() {
+        assert!(FilenameTemplate::new("").is_err());
+        assert!(FilenameTemplate::new("{stem").is_err());
+        assert!(FilenameTemplate::new("stem}").is_err());
+        assert!(FilenameTemplate::new("{unknown}.jpg").is_err());
+    }
+
+    #[test]
+    fn claims_tell_inputs_apart() {
+        let claims = OutputClaims::default();
+        let output = vec![PathBuf::from("out/IMG_0001.jpg")];
+        let jpg = claims.claim(Path::new("in/IMG_0001.jpg"), output.clone()).unwrap();
+        let png = claims.claim(Path::new("in/IMG_0001.png"), output.clone()).unwrap();
+        assert_eq!(jpg, output);
+        assert_eq!(png, vec![PathBuf::from("out/IMG_0001_png.jpg")]);
+
+        // the same input claims the same outputs again, e.g. when developed again
+        let again = claims.claim(Path::new("in/IMG_0001.png"), output).unwrap();
+        assert_eq!(again, png);
+    }
+}
@@ -0,0 +1,76 @@
+use std::{error::Error, fs::File, path::Path};
+
+use crate::entity::{DevelopRecord, DevelopStatus};
+
+/// Write the records of a batch run to a report file.
+///
+/// The format is picked by the extension of the path, `.csv` for CSV and JSON otherwise.
+pub fn write_report(path: &str, records: &[DevelopRecord]) -> Result<(), Box<dyn Error>> {
+    let is_csv = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        write_csv_report(path, records)
+    } else {
+        write_json_report(path, records)
+    }
+}
+
+fn write_json_report(path: &str, records: &[DevelopRecord]) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, records)?;
+    Ok(())
+}
+
+fn write_csv_report(path: &str, records: &[DevelopRecord]) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record([
+        "input",
+        "outputs",
+        "painter",
+        "status",
+        "reason",
+        "artist",
+        "lens_model",
+        "camera_maker",
+        "camera_model",
+        "aperture",
+        "focal_length",
+        "exposure_time",
+        "iso",
+        "datetime",
+    ])?;
+
+    for record in records {
+        let status = match record.status {
+            DevelopStatus::Developed => "developed",
+            DevelopStatus::Skipped => "skipped",
+            DevelopStatus::Failed => "failed",
+        };
+        let exif = record.exif.as_ref();
+        let field = |f: Option<&Option<String>>| {
+            f.and_then(|v| v.as_deref())
+                .unwrap_or("")
+                .trim()
+                .to_string()
+        };
+        writer.write_record([
+            record.input.clone(),
+            record.outputs.join(";"),
+            record.painter.clone(),
+            status.to_string(),
+            record.reason.clone().unwrap_or_default(),
+            field(exif.map(|e| &e.artist)),
+            field(exif.map(|e| &e.lens_model)),
+            field(exif.map(|e| &e.camera_maker)),
+            field(exif.map(|e| &e.camera_model)),
+            field(exif.map(|e| &e.aperture)),
+            field(exif.map(|e| &e.focal_length)),
+            field(exif.map(|e| &e.exposure_time)),
+            field(exif.map(|e| &e.iso)),
+            field(exif.map(|e| &e.datetime)),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::{debug, error, info, warn};
+use notify::{event::ModifyKind, EventKind, RecursiveMode, Watcher};
+use tokio::{
+    sync::mpsc,
+    task::{self, JoinSet},
+    time,
+};
+
+use crate::{
+    argument::WatchArguments,
+    command::{build_input_filter, Darkroom, EXIT_OK, EXIT_SETUP_FAILED},
+    entity::DevelopStatus,
+    utility::{
+        caption::{is_sidecar, sidecar_path, sidecar_stem},
+        input::{InputFile, InputFilter},
+    },
+};
+
+/// How often pending files are checked for being fully written.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Size & modification time of a photo & its caption sidecar, which tell whether it's changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Signature {
+    len: u64,
+    modified: Option<SystemTime>,
+    /// size & modification time of the caption sidecar if there is one
+    sidecar: Option<(u64, Option<SystemTime>)>,
+}
+
+impl Signature {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        let sidecar = fs::metadata(sidecar_path(path))
+            .ok()
+            .filter(|m| m.is_file())
+            .map(|m| (m.len(), m.modified().ok()));
+        Some(Signature {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            sidecar,
+        })
+    }
+}
+
+/// A file that is changed but may still be under writing.
+struct PendingFile {
+    input: InputFile,
+    signature: Signature,
+    stable_since: Instant,
+}
+
+/// Watch the input directory and develop new or changed photos once they are fully written.
+///
+/// A file is regarded as fully written if its size & modification time stay unchanged for the
+/// settle duration. Runs until interrupted by Ctrl-C, then waits for the photos under developing.
+pub async fn run(args: WatchArguments) -> u8 {
+    let settle = Duration::from_millis(args.settle);
+    let args = args.batch;
+    let darkroom = match Darkroom::setup(&args) {
+        Ok(d) => Arc::new(d),
+        Err(e) => {
+            error!("{}", e);
+            return EXIT_SETUP_FAILED;
+        }
+    };
+    let filter = match build_input_filter(&args) {
+        Ok(f) => f,
+        Err(e) => {
+            error!("{}", e);
+            return EXIT_SETUP_FAILED;
+        }
+    };
+    let input_root = match fs::canonicalize(&args.input) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("cannot watch input {}, cause: {}", args.input, e);
+            return EXIT_SETUP_FAILED;
+        }
+    };
+    // outputs may be written inside the input directory, never develop them again
+    let output_root = fs::canonicalize(&args.output).unwrap_or_else(|_| PathBuf::from(&args.output));
+
+    // forward filesystem events into the async world
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let _ = sender.send(res);
+    }) {
+        Ok(w) => w,
+        Err(e) => {
+            error!("cannot create filesystem watcher, cause: {}", e);
+            return EXIT_SETUP_FAILED;
+        }
+    };
+    let mode = match args.recursive {
+        true => RecursiveMode::Recursive,
+        false => RecursiveMode::NonRecursive,
+    };
+    if let Err(e) = watcher.watch(&input_root, mode) {
+        error!("cannot watch input {}, cause: {}", input_root.display(), e);
+        return EXIT_SETUP_FAILED;
+    }
+
+    info!(
+        "watching {} for new photos, developing them once unchanged for {:?}",
+        input_root.display(),
+        settle
+    );
+
+    let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+    let mut developed: HashMap<PathBuf, Signature> = HashMap::new();
+    let mut ticker = time::interval(POLL_INTERVAL);
+    let mut tasks = JoinSet::new();
+    // listen once, so a Ctrl-C between two iterations is never lost
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            result = &mut ctrl_c => {
+                if let Err(e) = result {
+                    error!("cannot listen for Ctrl-C, cause: {}", e);
+                }
+                info!(
+                    "stop watching {}, waiting for {} photos under developing",
+                    input_root.display(),
+                    tasks.len()
+                );
+                finish(&mut tasks).await;
+                return EXIT_OK;
+            }
+            event = receiver.recv() => {
+                let event = match event {
+                    Some(Ok(event)) => event,
+                    Some(Err(e)) => {
+                        warn!("filesystem watcher error, cause: {}", e);
+                        continue;
+                    }
+                    None => {
+                        error!("filesystem watcher stopped unexpectedly");
+                        finish(&mut tasks).await;
+                        return EXIT_SETUP_FAILED;
+                    }
+                };
+                // a removed caption sidecar changes the caption of its photos as well
+                let removed = matches!(event.kind, EventKind::Remove(_));
+                if !is_content_event(&event.kind) && !removed {
+                    continue;
+                }
+                let inputs = event
+                    .paths
+                    .into_iter()
+                    .filter(|path| !path.starts_with(&output_root))
+                    .flat_map(|path| match is_sidecar(&path) {
+                        true => sidecar_photos(&input_root, &path, args.recursive, &filter),
+                        false => to_input_file(&input_root, path, args.recursive, &filter)
+                            .into_iter()
+                            .collect(),
+                    });
+                for input in inputs {
+                    if let Some(signature) = Signature::of(&input.path) {
+                        debug!("file changed: {}", input.path.display());
+                        pending.insert(
+                            input.path.clone(),
+                            PendingFile { input, signature, stable_since: Instant::now() },
+                        );
+                    }
+                }
+            }
+            _ = ticker.tick() => {
+                while let Some(joined) = tasks.try_join_next() {
+                    log_join_error(joined);
+                }
+                let ready = collect_ready(&mut pending, &developed, settle);
+                for (input, signature) in ready {
+                    developed.insert(input.path.clone(), signature);
+
+                    let darkroom = darkroom.clone();
+                    tasks.spawn(async move {
+                        let record = darkroom.develop(input).await;
+                        if record.status == DevelopStatus::Developed {
+                            darkroom.save_manifest().await;
+                        }
+                        let reason = record.reason.as_deref().unwrap_or("");
+                        match record.status {
+                            DevelopStatus::Developed => info!(
+                                "developed {} into {}",
+                                record.input,
+                                record.outputs.join(", ")
+                            ),
+                            DevelopStatus::Skipped => warn!("skipped {}, {}", record.input, reason),
+                            DevelopStatus::Failed => {
+                                error!("cannot develop {}, {}", record.input, reason)
+                            }
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Wait for the develop tasks to finish, including their manifest saves.
+async fn finish(tasks: &mut JoinSet<()>) {
+    while let Some(joined) = tasks.join_next().await {
+        log_join_error(joined);
+    }
+}
+
+fn log_join_error(joined: Result<(), task::JoinError>) {
+    if let Err(e) = joined {
+        error!("failed to join develop task, cause: {}", e);
+    }
+}
+
+/// Whether the event may change the content of a file.
+fn is_content_event(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Modify(ModifyKind::Data(_))
+            | EventKind::Modify(ModifyKind::Name(_))
+            | EventKind::Modify(ModifyKind::Any)
+            | EventKind::Access(notify::event::AccessKind::Close(
+                notify::event::AccessMode::Write
+            ))
+    )
+}
+
+fn to_input_file(
+    input_root: &Path,
+    path: PathBuf,
+    recursive: bool,
+    filter: &InputFilter,
+) -> Option<InputFile> {
+    let relative = path.strip_prefix(input_root).ok()?.to_path_buf();
+    if !recursive && relative.components().count() != 1 {
+        return None;
+    }
+    if !filter.is_match(&relative) {
+        return None;
+    }
+    Some(InputFile { path, relative })
+}
+
+/// Photos the caption sidecar belongs to, i.e. those named by its stem next to it.
+fn sidecar_photos(
+    input_root: &Path,
+    sidecar: &Path,
+    recursive: bool,
+    filter: &InputFilter,
+) -> Vec<InputFile> {
+    let (stem, directory) = match (sidecar_stem(sidecar), sidecar.parent()) {
+        (Some(s), Some(d)) => (s, d),
+        _ => return Vec::new(),
+    };
+    let entries = match fs::read_dir(directory) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("cannot list photos of caption {}, cause: {}", sidecar.display(), e);
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| !is_sidecar(path) && path.file_stem() == Some(stem.as_ref()))
+        .filter_map(|path| to_input_file(input_root, path, recursive, filter))
+        .collect()
+}
+
+/// Take out the pending files that stay unchanged for the settle duration.
+///
+/// Files with the same signature as when they were last developed are dropped, so each change
+/// gets developed only once.
+fn collect_ready(
+    pending: &mut HashMap<PathBuf, PendingFile>,
+    developed: &HashMap<PathBuf, Signature>,
+    settle: Duration,
+) -> Vec<(InputFile, Signature)> {
+    let mut ready = Vec::new();
+    pending.retain(|path, p| {
+        let signature = match Signature::of(path) {
+            Some(s) => s,
+            None => return false, // removed or renamed away
+        };
+        if signature != p.signature {
+            // still under writing
+            p.signature = signature;
+            p.stable_since = Instant::now();
+            return true;
+        }
+        if p.stable_since.elapsed() < settle {
+            return true;
+        }
+        if developed.get(path) != Some(&signature) {
+            ready.push((p.input.clone(), signature));
+        }
+        false
+    });
+    ready.sort_by(|a, b| a.0.relative.cmp(&b.0.relative));
+    ready
+}